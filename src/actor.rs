@@ -1,18 +1,45 @@
 // Handles active objects like living entities and projectiles.
-use constants::{ACT_DROP, ACT_MOVES, ACT_SKILL, ACT_TURNS, ACT_WAIT, TILE_BLOOD};
+use constants::ACT_WAIT;
+use constants::{ACT_DROP, ACT_GIVE, ACT_MOVES, ACT_SKILL, ACT_THROW, ACT_TRADE};
+use constants::{ACT_TURNS, ACT_USE};
+use constants::{ITEM_CORPSE, ITEM_LIFE_GEM, SLOT_NONE, TILE_BLOOD};
+use constants::{TILE_MARSH, TILE_SCORCHED};
+use controller::{Ai, Controller};
 use csv;
 use inflector::Inflector;
 use item::Item;
 use item_effects;
+use loot;
 use plan::Plan;
+use progression;
+use projectile::{self, Flight};
 use skills::*;
 use skills_registry;
+use status::{Status, StatusKind};
 use std::cell::RefCell;
+use std::cmp;
 use std::collections::HashMap;
 use std::error::Error;
-use std::{cmp, i32, u16};
+use std::mem;
+use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use world::World;
 
+const SIGHT_RANGE: i32 = 12;
+const LEASH_RANGE: i32 = 8; // how far summons stray from their summoner
+const HEEL_RANGE: i32 = 2; // how close they keep while their side follows its leaders
+const THROW_RANGE: u16 = 4;
+const CARRY_BASE: u16 = 4; // weight anyone can carry, plus some per strength
+const CARRY_PER_STRENGTH: u16 = 3;
+
+static NEXT_UID: AtomicUsize = AtomicUsize::new(1);
+
+// How useful plain moves and attacks are, on the same scale as skills' scores.
+const UTILITY_ATTACK: i32 = 50;
+const UTILITY_HELP: i32 = 40;
+const UTILITY_MOVE: i32 = 20;
+const UTILITY_IDLE: i32 = 5;
+
 type ActorStats = (
     u8,     // kind
     char,   // glyph
@@ -24,12 +51,14 @@ type ActorStats = (
     u16,    // con
     u16,    // intel
     u16,    // dex
+    u16,    // percent chance to parry attacks from the front
 );
 
 thread_local!(static _ACTOR_CSV_CACHE: RefCell<HashMap<u8, ActorStats>> = RefCell::new(HashMap::new()));
 
 pub struct Actor {
     pub name: String,
+    pub uid: usize,
     pub kind: u8,
     pub team: usize,
     pub glyph: char,
@@ -41,29 +70,49 @@ pub struct Actor {
     pub health: u16,
     pub move_lag: u16,
     pub mana: u16,
-    pub intel: u16,
+    pub intel: u16, // base stats, before equipment
     pub con: u16,
     pub strength: u16,
+    pub parry: u16,    // percent chance to parry attacks from the front
     pub walls: String, // list of things I can't walk through
     skill_idx: usize,
 
     pub momentum: u8,
     pub stun: i16,
     pub invis: i16,
+    pub statuses: Vec<Status>,
+    pub cooldowns: HashMap<String, u16>,
+    pub winding_up: Option<(String, u16)>, // skill and turns left to prepare it
+    pub target: Option<(u16, u16)>,        // where the player aimed the skill
+    pub owner: Option<usize>,              // uid of whoever launched this projectile
+    pub flight: Option<Flight>,
+    pub kills: u16,
+    pub summoner: Option<usize>,    // uid of whoever summoned this actor
+    pub is_bound: bool,             // vanishes when its summoner dies
+    pub lifetime: Option<u16>,      // turns left before a summon vanishes
+    pub summons: u16,               // how many of my summons are still around
+    pub leash: Option<(u16, u16)>,  // where my summoner is
+    pub quarry: Option<(u16, u16)>, // the enemy my summoner is after
 
     pub log: Vec<(u32, String, usize)>,
     pub random_seed: u16,
     pub skills: Vec<String>,
+    pub learned: Vec<String>, // skills chosen when leveling up
     pub inventory: Vec<Item>,
+    pub chosen_item: Option<usize>, // inventory index to use or throw next
+    pub wanted_item: Option<usize>, // a teammate's inventory index to trade for, or a container's
+    pub trade_partner: Option<usize>, // uid of the teammate to trade with
 
     pub is_leader: bool,
     pub is_persistent: bool,
+    pub controller: Box<dyn Controller>,
     config: String,
 }
 
 impl Actor {
     pub fn new(kind: u8, level: u16, team: usize, pos: (u16, u16)) -> Actor {
         let mut actor = Actor {
+            uid: NEXT_UID.fetch_add(1, Ordering::Relaxed),
             kind,
             pos,
             level,
@@ -73,6 +122,7 @@ impl Actor {
             strength: 1,
             con: 1,
             intel: 1,
+            parry: 0,
             mana: 1,
             name: String::new(),
             walls: String::new(),
@@ -85,10 +135,28 @@ impl Actor {
             momentum: 0,
             skill_idx: 0,
             skills: Vec::new(),
+            learned: Vec::new(),
             time: 1,
             log: Vec::new(),
             inventory: Vec::new(),
+            chosen_item: None,
+            wanted_item: None,
+            trade_partner: None,
             invis: 0,
+            statuses: Vec::new(),
+            cooldowns: HashMap::new(),
+            winding_up: None,
+            target: None,
+            owner: None,
+            flight: None,
+            kills: 0,
+            summoner: None,
+            is_bound: false,
+            lifetime: None,
+            summons: 0,
+            leash: None,
+            quarry: None,
+            controller: Box::new(Ai),
             config: "config/glad/actor.csv".to_owned(),
         };
         actor.initialize(kind);
@@ -112,12 +180,164 @@ impl Actor {
         self.strength = row.6;
         self.con = row.8;
         self.intel = row.9;
-        self._initialize_inventory();
+        self.parry = row.10;
+        self.flight = Flight::new(self.kind);
+        self._initialize_progression();
+    }
+
+    fn _initialize_progression(&mut self) {
+        for level in 1..=self.level {
+            self._unlock_level(level);
+        }
+    }
+
+    fn _unlock_level(&mut self, level: u16) {
+        let unlocks = progression::unlocks(self.kind, level);
+        for (skill, replaces) in &unlocks {
+            if unlocks.len() == 1 || self.learned.contains(skill) {
+                self._unlock_skill(skill, replaces);
+            }
+        }
+    }
+
+    fn _unlock_skill(&mut self, skill: &str, replaces: &str) {
+        if self.has_skill(skill) {
+            return;
+        }
+        match self.skills.iter().position(|s| s == replaces) {
+            Some(idx) => self.skills[idx] = skill.to_owned(),
+            None => self.skills.push(skill.to_owned()),
+        }
+    }
+
+    /// Gains a level, unlocking new skills (`branch` picks between choices).
+    pub fn level_up(&mut self, branch: Option<&str>) {
+        self.level += 1;
+        self.log_action(&format!("reached level {}!", self.level));
+        if let Some(skill) = branch {
+            self.learned.push(skill.to_owned());
+        }
+        let old_skills = self.skills.clone();
+        let level = self.level;
+        self._unlock_level(level);
+        for skill in self.skills.clone() {
+            if !old_skills.contains(&skill) {
+                self.log_action(&format!("learned to {}!", skill));
+            }
+        }
+    }
+
+    fn _equipped(&self) -> impl Iterator<Item = &Item> {
+        self.inventory.iter().filter(|item| item.is_equipped)
+    }
+
+    pub fn strength(&self) -> u16 {
+        self.strength + self._equipped().map(|item| item.strength).sum::<u16>()
+    }
+
+    pub fn con(&self) -> u16 {
+        self.con + self._equipped().map(|item| item.con).sum::<u16>()
+    }
+
+    pub fn intel(&self) -> u16 {
+        self.intel + self._equipped().map(|item| item.intel).sum::<u16>()
+    }
+
+    /// How my stats would change if `action` were done with the item at `idx`.
+    pub fn stat_preview(&self, idx: usize, action: u8) -> Vec<(&'static str, u16, u16)> {
+        let item = &self.inventory[idx];
+        let worn: Vec<&Item> = self._equipped().collect();
+        let load_after = if action == ACT_USE && !item_effects::is_consumable(item.kind) {
+            self.load()
+        } else {
+            self.load() - item.weight
+        };
+        let would_wear: Vec<&Item> = if item.is_equipped {
+            worn.iter()
+                .cloned()
+                .filter(|worn| !ptr::eq(*worn, item))
+                .collect()
+        } else if action == ACT_USE && item.is_equipment() {
+            let mut would_wear: Vec<&Item> = worn
+                .iter()
+                .cloned()
+                .filter(|worn| worn.slot != item.slot)
+                .collect();
+            would_wear.push(item);
+            would_wear
+        } else {
+            worn.clone()
+        };
+        let now = self._stats_wearing(&worn, self.load());
+        let after = self._stats_wearing(&would_wear, load_after);
+        now.iter()
+            .zip(after.iter())
+            .filter(|(now, after)| now.1 != after.1)
+            .map(|(now, after)| (now.0, now.1, after.1))
+            .collect()
+    }
+
+    fn _stats_wearing(&self, worn: &[&Item], load: u16) -> [(&'static str, u16); 4] {
+        let bonus = |stat: fn(&Item) -> u16| worn.iter().map(|item| stat(item)).sum::<u16>();
+        let strength = self.strength + bonus(|item| item.strength);
+        let capacity = CARRY_BASE + CARRY_PER_STRENGTH * strength;
+        [
+            (
+                "health",
+                cmp::max(1, (self.con + bonus(|item| item.con)) * self.level),
+            ),
+            (
+                "mana",
+                cmp::max(1, (self.intel + bonus(|item| item.intel)) * self.level),
+            ),
+            ("strength", strength),
+            (
+                "move lag",
+                _lag(self.move_lag, bonus(|item| item.speed), capacity, load),
+            ),
+        ]
+    }
+
+    pub fn load(&self) -> u16 {
+        self.inventory.iter().map(|item| item.weight).sum()
+    }
+
+    /// How much I can carry before it slows me down.
+    pub fn capacity(&self) -> u16 {
+        CARRY_BASE + CARRY_PER_STRENGTH * self.strength()
+    }
+
+    /// What I can't walk through, minus whatever my equipment lets me pass.
+    pub fn walls(&self) -> String {
+        let passes: String = self._equipped().map(|item| item.passes.as_str()).collect();
+        self.walls
+            .chars()
+            .filter(|&wall| !passes.contains(wall))
+            .collect()
+    }
+
+    /// Wears the item at `idx`, taking off whatever was in the same slot.
+    pub fn equip(&mut self, idx: usize) {
+        let slot = self.inventory[idx].slot;
+        if slot == SLOT_NONE {
+            let log = format!("couldn't wear {}.", self.inventory[idx].name);
+            return self.log_action(&log);
+        }
+        for other in 0..self.inventory.len() {
+            if other != idx && self.inventory[other].slot == slot {
+                self.unequip(other);
+            }
+        }
+        self.inventory[idx].is_equipped = true;
+        let log = format!("put on {}.", self.inventory[idx].name);
+        self.log_action(&log);
     }
 
-    fn _initialize_inventory(&mut self) {
-        for kind in self.inventory.iter().map(|it| it.kind).collect::<Vec<u8>>() {
-            item_effects::use_on_actor(self, kind);
+    pub fn unequip(&mut self, idx: usize) {
+        if self.inventory[idx].is_equipped {
+            self.inventory[idx].is_equipped = false;
+            let log = format!("took off {}.", self.inventory[idx].name);
+            self.log_action(&log);
         }
     }
 
@@ -133,17 +353,17 @@ impl Actor {
     }
 
     pub fn max_health(&self) -> u16 {
-        cmp::max(1, self.con * self.level)
+        cmp::max(1, self.con() * self.level)
     }
 
     pub fn max_mana(&self) -> u16 {
-        cmp::max(1, self.intel * self.level)
+        cmp::max(1, self.intel() * self.level)
     }
 
     pub fn teleport(&mut self, wld: &World) -> (u16, u16) {
         loop {
-            let pos = (rand_int(wld.size.0 as u16), rand_int(wld.size.1 as u16));
-            if !self.walls.contains(wld.glyph_at(pos)) {
+            let pos = (rand_int(wld.size.0), rand_int(wld.size.1));
+            if !self.walls().contains(wld.glyph_at(pos)) {
                 self.pos = pos;
                 return self.pos;
             }
@@ -195,36 +415,83 @@ impl Actor {
             }
         }
         let skill = self.selected_skill();
-        self.log_action(&format!("switched to {}.", skill));
-    }
-
-    pub fn inventory(&mut self) {
-        self.log_action("turned out my pockets.");
-        for idx in 0..self.inventory.len() {
-            let log = &format!("had {}.", self.inventory[idx].name);
-            self.log_action(log);
+        match skills_registry::describe(&skill) {
+            Some(description) => {
+                self.log_action(&format!("switched to {} ({}).", skill, description))
+            }
+            None => self.log_action(&format!("switched to {}.", skill)),
         }
     }
 
     pub fn choose(&mut self, world: &World, plan: &Plan) -> u8 {
         if self.is_projectile() {
             return self.direction;
-        } else if skills_registry::choose_skill(self, world, plan) {
+        }
+        if let Some(idx) = self._choose_item_to_use(world, plan) {
+            self.chosen_item = Some(idx);
+            return ACT_USE;
+        }
+        if let Some(idx) = self._choose_item_to_drop() {
+            self.chosen_item = Some(idx);
+            return ACT_DROP;
+        }
+        let (move_utility, mv) = self._choose_move(world, plan);
+        let (skill_utility, skill) = skills_registry::choose_skill(self, world, plan);
+        if skill_utility > move_utility {
+            self.select_skill(&skill);
             return ACT_SKILL;
         }
+        mv
+    }
+
+    // Overloaded AI actors drop the heaviest thing they aren't wearing.
+    fn _choose_item_to_drop(&self) -> Option<usize> {
+        if self.load() <= self.capacity() || self._is_player_controlled() {
+            return None;
+        }
+        (0..self.inventory.len())
+            .filter(|&idx| !self.inventory[idx].is_equipped)
+            .max_by_key(|&idx| self.inventory[idx].weight)
+    }
+
+    fn _choose_item_to_use(&self, world: &World, plan: &Plan) -> Option<usize> {
+        let is_in_danger = plan.is_near_enemy(self.pos, self.team);
+        let can_revive = self._revivable_corpse(world, plan).is_some();
+        self.inventory.iter().position(|item| {
+            item_effects::should_use(self, item.kind, is_in_danger)
+                || (item.kind == ITEM_LIFE_GEM && can_revive)
+        })
+    }
+
+    // The corpse of a teammate alongside, with room for them to rise.
+    fn _revivable_corpse(&self, world: &World, plan: &Plan) -> Option<usize> {
+        world.items.iter().position(|item| {
+            item.kind == ITEM_CORPSE
+                && item.team == self.team
+                && item.owner.is_some()
+                && self._is_adjacent(item.pos)
+                && plan.whos_at(item.pos).is_none()
+        })
+    }
+
+    // The best move or attack, and how useful it is compared to using a skill.
+    fn _choose_move(&self, world: &World, plan: &Plan) -> (i32, u8) {
         let start_dir = self._choose_preferred_dir();
         let (mut best_value, mut best_direction) = (i32::MIN, start_dir);
         for mv in ACT_MOVES.iter().map(|offset| (start_dir + offset) % 9) {
-            let mut pos = world.neighbor(self.pos, mv, self.team, &self.walls);
+            let mut pos = world.neighbor(self.pos, mv, self.team, &self.walls());
             let mut movement = pos != self.pos;
             if !movement {
                 pos = world.offset(self.pos, mv)
             }
             if !self.is_hurt() && !plan.is_retreating(self.team) {
                 if let Some(&team) = plan.whos_at(pos) {
-                    if team != self.team || (pos != self.pos && self._can_help()) {
-                        return mv;
-                    } else if !self._can_displace(plan) {
+                    let can_help = pos != self.pos && self._can_help();
+                    if plan.is_hostile(self.team, team) {
+                        return (UTILITY_ATTACK, mv);
+                    } else if can_help && plan.is_allied(self.team, team) {
+                        return (UTILITY_HELP, mv);
+                    } else if !plan.is_allied(self.team, team) || !self._can_displace(plan) {
                         movement = false;
                     }
                 }
@@ -237,7 +504,10 @@ impl Actor {
                 }
             }
         }
-        best_direction
+        if best_value > self._value_of_pos(self.pos, plan) {
+            return (UTILITY_MOVE, best_direction);
+        }
+        (UTILITY_IDLE, best_direction)
     }
 
     fn _choose_preferred_dir(&self) -> u8 {
@@ -249,6 +519,22 @@ impl Actor {
 
     // How good this position is to the actor (larger is better)
     fn _value_of_pos(&self, pos: (u16, u16), plan: &Plan) -> i32 {
+        if let Some(home) = self.leash {
+            // summons heel while their side follows, and go after their summoner's quarry:
+            let range = if plan.is_following(self.team) {
+                HEEL_RANGE
+            } else {
+                LEASH_RANGE
+            };
+            if self._distance_squared(home) > range * range {
+                return -_distance_squared(pos, home);
+            }
+            if let Some(quarry) = self.quarry {
+                if plan.is_attacking(self.team) {
+                    return -_distance_squared(pos, quarry);
+                }
+            }
+        }
         let dist = plan.distance_to_goal(pos, self.team);
         if self._is_retreating(plan) {
             dist
@@ -261,16 +547,33 @@ impl Actor {
         &mut self,
         mv: u8,
         wld: &mut World,
-        plan: &Plan,
+        plan: &mut Plan,
         other: &mut Vec<&mut [Actor]>,
         spawn: &mut Vec<Actor>,
     ) {
         if self.stun != 0 {
             return;
         }
+        if let Some((skill, turns)) = self.winding_up.take() {
+            if turns > 1 {
+                return self.winding_up = Some((skill, turns - 1));
+            }
+            self.select_skill(&skill);
+            return skills_registry::release_skill(self, wld, plan, spawn);
+        }
+        let mv = if self.has_status(StatusKind::Confused) && mv < ACT_WAIT && rand_int(2) == 0 {
+            rand_int(8) as u8
+        } else {
+            mv
+        };
         match mv {
             ACT_SKILL => skills_registry::use_skill(self, wld, plan, spawn),
             ACT_DROP => self._act_drop_item(wld),
+            ACT_USE => self._act_use_item(wld, plan),
+            ACT_THROW => self._act_throw_item(wld, plan, other),
+            ACT_GIVE => self._act_give_item(wld, other),
+            ACT_TRADE => self._act_trade_item(other),
+            _ if self.flight.is_some() => self._act_fly(wld, plan, other),
             _ => {
                 if self.is_mobile() {
                     self._act_move(mv, wld, plan, other);
@@ -280,8 +583,18 @@ impl Actor {
         };
     }
 
-    fn _act_move(&mut self, mv: u8, wld: &mut World, plan: &Plan, other: &mut Vec<&mut [Actor]>) {
-        let mut pos = wld.neighbor(self.pos, mv, self.team, &self.walls);
+    fn _act_move(
+        &mut self,
+        mv: u8,
+        wld: &mut World,
+        plan: &mut Plan,
+        other: &mut Vec<&mut [Actor]>,
+    ) {
+        let mut pos = if self.has_status(StatusKind::Rooted) {
+            self.pos
+        } else {
+            wld.neighbor(self.pos, mv, self.team, &self.walls())
+        };
         let movement = self.pos != pos;
         if !movement {
             pos = wld.offset(self.pos, mv);
@@ -294,21 +607,136 @@ impl Actor {
                 }
             }
         } else if movement {
+            let from = self.pos;
             self.pos = pos;
             self.gain_momentum(1);
+            self._provoke_opportunity_attacks(from, wld, plan, other);
             passive_effect!(passive_grow => self, wld);
         } else if ACT_MOVES.contains(&mv) {
             self._act_push_wall(wld, mv);
         }
     }
 
+    fn _act_fly(&mut self, wld: &mut World, plan: &mut Plan, other: &mut Vec<&mut [Actor]>) {
+        let speed = self.flight.as_ref().map_or(1, |flight| flight.speed);
+        for _step in 0..speed {
+            let is_returning = self
+                .flight
+                .as_ref()
+                .is_some_and(|flight| flight.is_returning);
+            if !self.is_alive() || (is_returning && !self._steer_home(wld, other)) {
+                return;
+            }
+            if wld.neighbor(self.pos, self.direction, self.team, &self.walls()) == self.pos {
+                self._act_bounce(wld);
+                continue;
+            }
+            let direction = self.direction;
+            self._act_move(direction, wld, plan, other);
+            let in_range = self.flight.as_mut().is_some_and(|flight| flight.advance());
+            if self.momentum == 0 || !in_range {
+                self._act_fall(wld);
+            }
+        }
+    }
+
+    // Bounces off walls if it still can, otherwise turns back or drops.
+    fn _act_bounce(&mut self, wld: &mut World) {
+        let can_ricochet = match self.flight {
+            Some(ref mut flight) if flight.ricochet > 0 => {
+                flight.ricochet -= 1;
+                true
+            }
+            _ => false,
+        };
+        if !can_ricochet {
+            return self._act_fall(wld);
+        }
+        let (pos, team, walls) = (self.pos, self.team, &self.walls());
+        self.direction = projectile::reflect(self.direction, &|dir| {
+            wld.neighbor(pos, dir, team, walls) != pos
+        });
+    }
+
+    fn _act_fall(&mut self, wld: &mut World) {
+        if self
+            .flight
+            .as_mut()
+            .is_some_and(|flight| flight.turn_back())
+        {
+            return self.gain_momentum(1);
+        }
+        self.act_die(wld);
+    }
+
+    // Returning projectiles head for their owner, who catches them.
+    fn _steer_home(&mut self, wld: &mut World, other: &mut Vec<&mut [Actor]>) -> bool {
+        let owner = self.owner;
+        let thrower = other
+            .iter_mut()
+            .flat_map(|actors| actors.iter_mut())
+            .find(|actor| Some(actor.uid) == owner && actor.is_combatant());
+        match thrower {
+            Some(thrower) if self._is_adjacent(thrower.pos) => {
+                thrower.log_action(&format!("caught {}.", self.name));
+                self.health = 0;
+                false
+            }
+            Some(thrower) => {
+                if let Some(dir) = wld.direction_to(self.pos, thrower.pos) {
+                    self.direction = dir;
+                }
+                true
+            }
+            None => {
+                self.act_die(wld);
+                false
+            }
+        }
+    }
+
     fn _act_push_wall(&mut self, world: &mut World, action: u8) {
+        if let Some(idx) = world.container_at(world.offset(self.pos, action)) {
+            return self._act_open_container(world, idx);
+        }
         if let Some(treasure) = world.push_wall(self.pos, action, &self.inventory) {
             self.log_action(&format!("pulled on {}.", treasure.name));
             if treasure.can_keep {
                 self.log_action(&format!("got {}.", treasure.name));
             }
-            self._add_to_inventory(treasure);
+            self.add_to_inventory(treasure);
+        }
+    }
+
+    /// Takes the wanted item, or else whatever fits, from a container.
+    fn _act_open_container(&mut self, world: &mut World, idx: usize) {
+        let wanted = self.wanted_item.take();
+        let name = world.items[idx].name.clone();
+        if !world.open_container(idx, &self.inventory) {
+            return self.log_action(&format!("pulled on {}.", name));
+        }
+        let contents = mem::take(&mut world.items[idx].contents);
+        if contents.is_empty() {
+            return self.log_action(&format!("found {} empty.", name));
+        }
+        if wanted.is_none() {
+            let names: Vec<&str> = contents.iter().map(|item| item.name.as_str()).collect();
+            self.log_action(&format!("found {} in {}.", names.join(", "), name));
+            if self._is_player_controlled() {
+                // players pick through it for themselves:
+                world.items[idx].contents = contents;
+                return;
+            }
+        }
+        for (item_idx, item) in contents.into_iter().enumerate() {
+            let can_carry =
+                self._is_player_controlled() || self.load() + item.weight <= self.capacity();
+            if can_carry && wanted.is_none_or(|wanted| wanted == item_idx) {
+                self.log_action(&format!("got {}.", item.name));
+                self.add_to_inventory(item);
+            } else {
+                world.items[idx].contents.push(item);
+            }
         }
     }
 
@@ -325,34 +753,219 @@ impl Actor {
     fn _act_get_all(&mut self, world: &mut World) {
         let mut idx = 0;
         while idx < world.items.len() {
-            if self.pos == world.items[idx].pos && world.items[idx].can_get {
+            let can_carry = self._is_player_controlled()
+                || self.load() + world.items[idx].weight <= self.capacity();
+            if self.pos == world.items[idx].pos && world.items[idx].can_get && can_carry {
                 let item = world.items.remove(idx);
                 self.log_action(&format!("got {}.", item.name));
-                self._add_to_inventory(item);
+                self.add_to_inventory(item);
             } else {
                 idx += 1;
             }
         }
     }
 
-    fn _add_to_inventory(&mut self, item: Item) {
-        item_effects::use_on_actor(self, item.kind);
-        if item.can_keep {
-            self.inventory.push(item);
-            self.inventory
-                .sort_by(|a, b| a.kind.partial_cmp(&b.kind).unwrap());
+    pub fn add_to_inventory(&mut self, item: Item) {
+        if !item.can_keep {
+            item_effects::use_on_actor(self, item.kind);
+            return;
+        }
+        let is_slot_free = !self._equipped().any(|worn| worn.slot == item.slot);
+        let (kind, should_equip) = (item.kind, item.is_equipment() && is_slot_free);
+        self.inventory.push(item);
+        self.inventory
+            .sort_by(|a, b| a.kind.partial_cmp(&b.kind).unwrap());
+        if should_equip {
+            let idx = self
+                .inventory
+                .iter()
+                .rposition(|it| it.kind == kind)
+                .unwrap();
+            self.equip(idx);
         }
     }
 
     fn _act_drop_item(&mut self, world: &mut World) {
-        if let Some(item) = self.inventory.pop() {
-            self.log_action(&format!("dropped {}.", item.name));
-            let pos = world.neighbor(self.pos, self.direction, self.team, "");
-            world.add_item(item, pos);
-            let kind = self.kind;
-            return self.initialize(kind);
+        let idx = match self.chosen_item.take() {
+            Some(idx) if idx < self.inventory.len() => idx,
+            _ if !self.inventory.is_empty() => self.inventory.len() - 1,
+            _ => return self.log_action("had nothing to drop."),
+        };
+        let mut item = self.inventory.remove(idx);
+        self.log_action(&format!("dropped {}.", item.name));
+        item.is_equipped = false;
+        let pos = world.neighbor(self.pos, self.direction, self.team, "");
+        world.add_item(item, pos);
+    }
+
+    // The item the player picked, or else the first one that fits.
+    fn _take_chosen_item(&mut self, fits: fn(&Item) -> bool) -> Option<Item> {
+        let idx = match self.chosen_item.take() {
+            Some(idx) if idx < self.inventory.len() => idx,
+            _ => self.inventory.iter().position(fits)?,
+        };
+        let mut item = self.inventory.remove(idx);
+        item.is_equipped = false;
+        Some(item)
+    }
+
+    fn _act_use_item(&mut self, wld: &mut World, plan: &Plan) {
+        let chosen = self.chosen_item.and_then(|idx| self.inventory.get(idx));
+        if chosen.is_some_and(|item| item.kind == ITEM_LIFE_GEM) {
+            return self._act_use_life_gem(wld, plan);
+        }
+        if let Some(idx) = self.chosen_item {
+            if self
+                .inventory
+                .get(idx)
+                .is_some_and(|item| item.is_equipment())
+            {
+                self.chosen_item = None;
+                if self.inventory[idx].is_equipped {
+                    return self.unequip(idx);
+                }
+                return self.equip(idx);
+            }
+        }
+        match self._take_chosen_item(|item| item_effects::is_consumable(item.kind)) {
+            Some(item) => {
+                self.log_action(&format!("used {}.", item.name));
+                item_effects::use_on_actor(self, item.kind);
+            }
+            None => self.log_action("had nothing to use."),
         }
-        self.log_action("had nothing to drop.")
+    }
+
+    // Spends the gem to raise the teammate whose corpse lies alongside.
+    fn _act_use_life_gem(&mut self, wld: &mut World, plan: &Plan) {
+        let corpse_idx = match self._revivable_corpse(wld, plan) {
+            Some(corpse_idx) => corpse_idx,
+            None => {
+                self.chosen_item = None;
+                return self.log_action("had no one to revive.");
+            }
+        };
+        if let Some(gem) = self._take_chosen_item(|item| item.kind == ITEM_LIFE_GEM) {
+            let corpse = wld.items.remove(corpse_idx);
+            self.log_action(&format!("held {} over {}.", gem.name, corpse.name));
+            if let Some(uid) = corpse.owner {
+                wld.revive(uid, corpse.pos);
+            }
+        }
+    }
+
+    /// Brings a fallen actor back on its feet with half its health.
+    pub fn revive(&mut self, pos: (u16, u16)) {
+        self.health = cmp::max(1, self.max_health() / 2);
+        self.invis = 0;
+        self.stun = 0;
+        self.pos = pos;
+        self.log_action("was brought back to life!");
+    }
+
+    // Thrown consumables work on whoever they hit; anything else lands.
+    fn _act_throw_item(&mut self, wld: &mut World, plan: &Plan, other: &mut Vec<&mut [Actor]>) {
+        let item = match self._take_chosen_item(|_item| true) {
+            Some(item) => item,
+            None => return self.log_action("had nothing to throw."),
+        };
+        self.log_action(&format!("threw {}.", item.name));
+        let walls = self.walls();
+        let mut pos = self.pos;
+        for _step in 0..THROW_RANGE {
+            let next = wld.neighbor(pos, self.direction, self.team, &walls);
+            if next == pos {
+                break;
+            }
+            pos = next;
+            if plan.whos_at(pos).is_some() {
+                break;
+            }
+        }
+        if item_effects::is_consumable(item.kind) {
+            for actors in other {
+                if let Some(actor) = actors.iter_mut().find(|a| a._is_blocking(pos)) {
+                    actor.log_action(&format!("was splashed with {}.", item.name));
+                    item_effects::use_on_actor(actor, item.kind);
+                    return;
+                }
+            }
+        }
+        wld.add_item(item, pos);
+    }
+
+    /// Whether the other actor is close enough to hand things to.
+    pub fn is_trade_partner(&self, other: &Actor) -> bool {
+        other.team == self.team
+            && other.is_playable()
+            && other.pos != self.pos
+            && self._is_adjacent(other.pos)
+    }
+
+    // Of the adjacent teammates, the one ahead is preferred.
+    fn _trade_partner<'a>(
+        &self,
+        wld: &World,
+        other: &'a mut Vec<&mut [Actor]>,
+    ) -> Option<&'a mut Actor> {
+        let ahead = wld.offset(self.pos, self.direction);
+        let mut teammates: Vec<&mut Actor> = other
+            .iter_mut()
+            .flat_map(|actors| actors.iter_mut())
+            .filter(|actor| self.is_trade_partner(actor))
+            .collect();
+        teammates.sort_by_key(|actor| actor.pos != ahead);
+        teammates.into_iter().next()
+    }
+
+    // Hands the chosen item to an adjacent teammate.
+    fn _act_give_item(&mut self, wld: &World, other: &mut Vec<&mut [Actor]>) {
+        let teammate = match self._trade_partner(wld, other) {
+            Some(teammate) => teammate,
+            None => {
+                self.chosen_item = None;
+                return self.log_action("had no one to give anything to.");
+            }
+        };
+        if let Some(item) = self._take_chosen_item(|_item| true) {
+            self.log_action(&format!("gave {} to {}.", item.name, teammate.name));
+            teammate.log_action(&format!("got {} from {}.", item.name, self.name));
+            teammate.add_to_inventory(item);
+        }
+    }
+
+    // Swaps the chosen item for the one wanted from an adjacent teammate.
+    fn _act_trade_item(&mut self, other: &mut Vec<&mut [Actor]>) {
+        let wanted = self.wanted_item.take();
+        let partner = self.trade_partner.take();
+        let teammate = other
+            .iter_mut()
+            .flat_map(|actors| actors.iter_mut())
+            .find(|actor| Some(actor.uid) == partner && self.is_trade_partner(actor));
+        let teammate = match teammate {
+            Some(teammate) => teammate,
+            None => {
+                self.chosen_item = None;
+                return self.log_action("had no one to trade with.");
+            }
+        };
+        let mut theirs = match wanted {
+            Some(idx) if idx < teammate.inventory.len() => teammate.inventory.remove(idx),
+            _ => {
+                self.chosen_item = None;
+                return self.log_action(&format!("had nothing to take from {}.", teammate.name));
+            }
+        };
+        theirs.is_equipped = false;
+        if let Some(mine) = self._take_chosen_item(|_item| true) {
+            let txt = format!("{} for {}", mine.name, theirs.name);
+            self.log_action(&format!("traded {} with {}.", txt, teammate.name));
+            teammate.log_action(&format!("got {} from {}.", mine.name, self.name));
+            teammate.add_to_inventory(mine);
+        } else {
+            self.log_action(&format!("took {} from {}.", theirs.name, teammate.name));
+        }
+        self.add_to_inventory(theirs);
     }
 
     fn _act_drop_all(&mut self, world: &mut World) {
@@ -362,23 +975,111 @@ impl Actor {
         }
     }
 
-    fn _act_touch(&mut self, other: &mut Actor, world: &mut World, action: u8, plan: &Plan) {
-        if other.is_enemy_of(self.team) && self.strength > 0 {
+    fn _act_touch(&mut self, other: &mut Actor, world: &mut World, action: u8, plan: &mut Plan) {
+        if !plan.is_allied(self.team, other.team) && self.strength > 0 {
+            if plan.provoke(self.team, other.team) {
+                let msg = format!("{} turned hostile!", other.name.to_sentence_case());
+                world.log_global(&msg, other.pos, false);
+            } else if !plan.is_aware(other.team) {
+                self.log_interaction("surprised", other);
+                other.stun(1);
+            }
+            if other._act_parry(self, action) {
+                return self.lose_momentum(1);
+            }
             passive_effect!(passive_trip => self, action, other);
             passive_effect!(passive_whirl => self, action, other);
             passive_effect!(passive_backstab => self, action, other);
             passive_effect!(passive_slam => self, action, other, world, plan);
-            return self._act_hit(other, world);
+            passive_effect!(passive_burn => self, other);
+            self._act_hit(other, world);
+            passive_effect!(passive_counter => other, self, world);
+            return;
         } else if self._can_displace(plan) && other.is_mobile() {
             return self._act_displace(other, world);
         }
-        passive_effect!(passive_heal => self, other, world);
+        passive_effect!(passive_heal => self, other, world, plan);
         self._act_help(other)
     }
 
+    // Attacks from the front can be parried, or blocked (even projectiles).
+    fn _act_parry(&mut self, attacker: &mut Actor, action: u8) -> bool {
+        self._parry_with_roll(attacker, action, rand_int(100))
+    }
+
+    // Parries when the roll, out of 100, falls under the chance to.
+    fn _parry_with_roll(&mut self, attacker: &mut Actor, action: u8, roll: u16) -> bool {
+        let angle = (i16::from(action) - i16::from(self.direction)).abs();
+        let is_frontal = angle == 3 || angle == 4 || angle == 5;
+        if !is_frontal || !self._can_react() {
+            return false;
+        }
+        let chance = if self.has_skill("passive_block") {
+            magnitude("passive_block")
+        } else if attacker.is_projectile() {
+            return false;
+        } else {
+            self.parry
+        };
+        if roll >= chance {
+            return false;
+        }
+        let verb = if self.has_skill("passive_block") {
+            "blocked"
+        } else {
+            "parried"
+        };
+        self.log_interaction(verb, attacker);
+        true
+    }
+
+    // Enemies left behind get a free attack on an actor moving away.
+    fn _provoke_opportunity_attacks(
+        &mut self,
+        from: (u16, u16),
+        wld: &mut World,
+        plan: &Plan,
+        other: &mut Vec<&mut [Actor]>,
+    ) {
+        if self.is_projectile() {
+            return;
+        }
+        let threats: Vec<(u16, u16)> = (0..8)
+            .map(|dir| wld.offset(from, dir))
+            .filter(|&pos| pos != from && !self._is_adjacent(pos))
+            .filter(|&pos| match plan.whos_at(pos) {
+                Some(&team) => plan.is_hostile(self.team, team),
+                None => false,
+            })
+            .collect();
+        if threats.is_empty() {
+            return;
+        }
+        for actors in other {
+            for actor in actors.iter_mut() {
+                if threats.contains(&actor.pos) && actor._can_react() && self.is_alive() {
+                    actor.log_interaction("struck at", self);
+                    self.hurt(cmp::max(1, actor.strength() * actor.level / 2), wld);
+                }
+            }
+        }
+    }
+
+    fn _is_player_controlled(&self) -> bool {
+        self.controller.is_human()
+    }
+
+    fn _can_react(&self) -> bool {
+        self.is_combatant() && self.is_mobile() && self.stun == 0 && !self.is_winding_up()
+    }
+
+    fn _is_adjacent(&self, pos: (u16, u16)) -> bool {
+        self._distance_squared(pos) <= 2
+    }
+
     fn _act_displace(&mut self, other: &mut Actor, world: &mut World) {
-        if !self.walls.contains(world.glyph_at(other.pos))
-            && !other.walls.contains(world.glyph_at(self.pos))
+        if !self.walls().contains(world.glyph_at(other.pos))
+            && !other.walls().contains(world.glyph_at(self.pos))
         {
             let new_pos = other.pos;
             other.pos = (self.pos.0, self.pos.1);
@@ -400,8 +1101,27 @@ impl Actor {
 
     fn _act_hit(&mut self, other: &mut Actor, world: &mut World) {
         self.log_interaction("hit", other);
-        self.lose_momentum(1);
-        other.hurt(self.strength * self.level, world);
+        let pierces = match self.flight {
+            Some(ref mut flight) if flight.pierce > 0 => {
+                flight.pierce -= 1;
+                true
+            }
+            _ => false,
+        };
+        if !pierces {
+            self.lose_momentum(1);
+        }
+        other.hurt(self.strength() * self.level, world);
+        let killed = !other.is_alive();
+        match self.owner {
+            Some(owner) => {
+                let verb = if killed { "killed" } else { "hit" };
+                let txt = format!("{} {} with {}.", verb, other.name, self.name);
+                world.credit(owner, &txt, killed);
+            }
+            None if killed && !self.is_projectile() => self.kills += 1,
+            None => {} // traps and other stray projectiles take no credit
+        }
         if self.momentum > 0 {
             self.pos = other.pos;
         }
@@ -414,21 +1134,51 @@ impl Actor {
             let msg = format!("{} {}!", self.name.to_sentence_case(), verb);
             world.log_global(&msg, self.pos, self._is_important());
         }
+        // only creatures that get around leave loot, not towers, generators or trees:
+        if self.team != 0 && self.summoner.is_none() && self.is_mobile() && !self.is_projectile() {
+            if let Some(item) = loot::for_death(self.kind, self.level, self.team) {
+                self.inventory.push(item);
+            }
+        }
         self._act_drop_all(world);
         self.is_leader = false;
+        self.statuses.clear();
+        self.winding_up = None;
         if self.is_flesh() {
-            world.add_item(Item::new(0, self.level, self.team), self.pos);
+            let mut corpse = Item::new(ITEM_CORPSE, self.level, self.team);
+            if self.is_persistent {
+                corpse.owner = Some(self.uid);
+            }
+            world.add_item(corpse, self.pos);
             world.change_tiles(self.pos, TILE_BLOOD);
         }
         self.invis = -1; // 👻
     }
 
+    /// Leaves the battle without a trace, as summons do.
+    pub fn act_vanish(&mut self, world: &mut World) {
+        let msg = format!("{} vanished!", self.name.to_sentence_case());
+        world.log_global(&msg, self.pos, false);
+        self._act_drop_all(world);
+        self.health = 0;
+        self.statuses.clear();
+        self.winding_up = None;
+        self.invis = -1;
+    }
+
     pub fn act_exert(&mut self, amt: u16, action: &str) {
         self.mana -= cmp::min(self.mana, amt);
         self.log_action(action);
     }
 
     pub fn hurt(&mut self, amt: u16, world: &mut World) {
+        let amt = if self.has_status(StatusKind::Shielded) {
+            amt.div_ceil(2)
+        } else if self.is_winding_up() {
+            amt + amt / 2
+        } else {
+            amt
+        };
         if amt < self.health {
             return self.health -= amt;
         }
@@ -436,10 +1186,110 @@ impl Actor {
     }
 
     pub fn stun(&mut self, amt: i16) {
+        if let Some((skill, _turns)) = self.winding_up.take() {
+            self.log_action(&format!("lost my concentration and couldn't {}.", skill));
+        }
+        self.target = None;
         self.stun = amt;
         self.lose_momentum(1);
     }
 
+    pub fn afflict(&mut self, kind: StatusKind, turns: u16) {
+        if !self.is_combatant() || turns == 0 {
+            return;
+        }
+        let status = Status::new(kind, turns);
+        if let Some(cancelled) = status.cancels() {
+            if self.has_status(cancelled) {
+                return self.cure(cancelled);
+            }
+        }
+        if let Some(existing) = self.statuses.iter_mut().find(|s| s.kind == kind) {
+            return existing.reapply(turns);
+        }
+        self.log_action(&format!("became {}.", status.name()));
+        self.statuses.push(status);
+    }
+
+    pub fn cure(&mut self, kind: StatusKind) {
+        if let Some(idx) = self.statuses.iter().position(|s| s.kind == kind) {
+            let status = self.statuses.remove(idx);
+            self.log_action(&format!("was no longer {}.", status.name()));
+        }
+    }
+
+    pub fn wind_up(&mut self, skill: &str, turns: u16) {
+        self.winding_up = Some((skill.to_owned(), turns));
+    }
+
+    /// Actors winding up a skill are committed to it and take extra damage.
+    pub fn is_winding_up(&self) -> bool {
+        self.winding_up.is_some()
+    }
+
+    pub fn cool_down(&mut self, skill: &str, turns: u16) {
+        if turns > 0 {
+            self.cooldowns.insert(skill.to_owned(), turns);
+        }
+    }
+
+    pub fn cooldown(&self, skill: &str) -> u16 {
+        *self.cooldowns.get(skill).unwrap_or(&0)
+    }
+
+    fn _tick_cooldowns(&mut self) {
+        for turns in self.cooldowns.values_mut() {
+            *turns -= 1;
+        }
+        self.cooldowns.retain(|_skill, turns| *turns > 0);
+    }
+
+    pub fn has_status(&self, kind: StatusKind) -> bool {
+        self.statuses.iter().any(|s| s.kind == kind)
+    }
+
+    fn _linger_in_effects(&mut self, world: &mut World) {
+        let mut effects = mem::take(&mut world.effects);
+        for effect in effects.iter().filter(|effect| effect.is_lingering()) {
+            if self.is_alive() && effect.cells(world).contains(&self.pos) {
+                effect.hurt(self, world);
+            }
+        }
+        effects.append(&mut world.effects);
+        world.effects = effects;
+    }
+
+    fn _tick_statuses(&mut self, world: &mut World) {
+        if let Some(tile) = world.tile_type_at(self.pos) {
+            if TILE_MARSH.contains(&tile) && self.is_mobile() {
+                self.afflict(StatusKind::Slowed, 2);
+            }
+        }
+        if self.has_status(StatusKind::Burning) {
+            match world.glyph_at(self.pos) {
+                '~' => self.cure(StatusKind::Burning),
+                _ => world.change_tiles(self.pos, TILE_SCORCHED),
+            }
+        }
+        let mut damage = 0;
+        for status in &self.statuses {
+            match status.kind {
+                StatusKind::Poisoned => damage += status.stacks,
+                StatusKind::Burning => damage += 2,
+                _ => {}
+            }
+        }
+        for idx in (0..self.statuses.len()).rev() {
+            if !self.statuses[idx].tick() {
+                let status = self.statuses.remove(idx);
+                self.log_action(&format!("was no longer {}.", status.name()));
+            }
+        }
+        if damage > 0 {
+            self.hurt(damage, world);
+        }
+    }
+
     pub fn gain_momentum(&mut self, _amt: u8) {
         self.momentum = cmp::max(self.momentum, 1);
     }
@@ -455,7 +1305,7 @@ impl Actor {
         if !self.is_projectile() && self.is_mobile() {
             self.recover(1);
             self.mana = cmp::min(self.max_mana(), self.mana + 1);
-            if self.walls.contains(world.glyph_at(self.pos)) {
+            if self.walls().contains(world.glyph_at(self.pos)) {
                 self.hurt(5, world);
             }
             if self.is_hurt() && self.stun == 0 && rand_int(self.health) == 0 {
@@ -467,9 +1317,19 @@ impl Actor {
                 self._act_get_all(world);
             }
         }
+        if self.is_combatant() {
+            self._linger_in_effects(world);
+            self._tick_statuses(world);
+            self._tick_cooldowns();
+        }
         if self.invis > 0 {
             self.invis -= 1;
         }
+        match self.lifetime {
+            Some(turns) if turns <= 1 && self.is_alive() => self.act_vanish(world),
+            Some(turns) => self.lifetime = Some(turns - 1),
+            None => {}
+        }
     }
 
     pub fn recover(&mut self, amt: u16) {
@@ -490,6 +1350,14 @@ impl Actor {
         }
     }
 
+    // Between battles, mana comes back but only half the wounds heal.
+    pub fn rest(&mut self) {
+        if self.is_alive() {
+            self.health += self.max_health().saturating_sub(self.health) / 2;
+            self.mana = self.max_mana();
+        }
+    }
+
     pub fn is_alive(&self) -> bool {
         self.health > 0
     }
@@ -514,7 +1382,21 @@ impl Actor {
     }
 
     pub fn is_ready_to_act(&self, time: u32) -> bool {
-        self.is_alive() && (time + u32::from(self.random_seed)) % u32::from(self.move_lag) == 0
+        self.is_alive()
+            && (time + u32::from(self.random_seed)).is_multiple_of(u32::from(self.move_lag()))
+    }
+
+    /// How many ticks pass between turns, after haste or slowness.
+    pub fn move_lag(&self) -> u16 {
+        let speed = self._equipped().map(|item| item.speed).sum::<u16>();
+        let move_lag = _lag(self.move_lag, speed, self.capacity(), self.load());
+        if self.has_status(StatusKind::Hasted) {
+            cmp::max(1, move_lag / 2)
+        } else if self.has_status(StatusKind::Slowed) {
+            move_lag * 2
+        } else {
+            move_lag
+        }
     }
 
     pub fn is_mobile(&self) -> bool {
@@ -533,8 +1415,8 @@ impl Actor {
         !self.is_projectile() && !self.is_artificial() && self.is_mobile()
     }
 
-    pub fn is_enemy_of(&self, team: usize) -> bool {
-        self.team != team && self.is_alive()
+    pub fn is_enemy_of(&self, team: usize, plan: &Plan) -> bool {
+        plan.is_hostile(self.team, team) && self.is_alive()
     }
 
     fn _can_help(&self) -> bool {
@@ -542,11 +1424,64 @@ impl Actor {
     }
 
     pub fn is_near(&self, pos: (u16, u16)) -> bool {
-        let (dx, dy) = (
-            i32::from(self.pos.0) - i32::from(pos.0),
-            i32::from(self.pos.1) - i32::from(pos.1),
-        );
-        dx * dx + dy * dy <= 18
+        self._distance_squared(pos) <= 18
+    }
+
+    /// Stealthy actors can only be seen from an adjacent cell.
+    pub fn can_see(&self, other: &Actor, world: &World) -> bool {
+        let range = if other.invis == 0 { SIGHT_RANGE } else { 1 };
+        self._can_see_within(other.pos, range, world)
+    }
+
+    /// Casts rays to the edge of sight, stopping each at the first opaque cell.
+    /// Cells seen along several rays are listed more than once.
+    pub fn field_of_view(&self, world: &World, opaque: &[bool]) -> Vec<(u16, u16)> {
+        let mut cells = vec![self.pos];
+        let (x, y) = (i32::from(self.pos.0), i32::from(self.pos.1));
+        let (max_x, max_y) = (i32::from(world.size.0) - 1, i32::from(world.size.1) - 1);
+        let mut edge = Vec::new();
+        for step in -SIGHT_RANGE..=SIGHT_RANGE {
+            edge.push((x + step, y - SIGHT_RANGE));
+            edge.push((x + step, y + SIGHT_RANGE));
+            edge.push((x - SIGHT_RANGE, y + step));
+            edge.push((x + SIGHT_RANGE, y + step));
+        }
+        for (xx, yy) in edge {
+            let to = (xx.max(0).min(max_x) as u16, yy.max(0).min(max_y) as u16);
+            for pos in world.line(self.pos, to) {
+                if self._distance_squared(pos) > SIGHT_RANGE * SIGHT_RANGE + SIGHT_RANGE {
+                    break;
+                }
+                cells.push(pos);
+                if opaque[(pos.1 * world.size.0 + pos.0) as usize] {
+                    break;
+                }
+            }
+        }
+        cells
+    }
+
+    fn _can_see_within(&self, pos: (u16, u16), range: i32, world: &World) -> bool {
+        self._distance_squared(pos) <= range * range + range && world.is_in_view(self.pos, pos)
+    }
+
+    fn _distance_squared(&self, pos: (u16, u16)) -> i32 {
+        _distance_squared(self.pos, pos)
+    }
+
+    /// The closest enemy the actor can see, if any.
+    pub fn nearest_enemy_in_sight(
+        &self,
+        actors: &[Actor],
+        world: &World,
+        plan: &Plan,
+    ) -> Option<(u16, u16)> {
+        actors
+            .iter()
+            .filter(|other| other.is_combatant() && other.is_enemy_of(self.team, plan))
+            .filter(|other| self.can_see(other, world))
+            .map(|other| other.pos)
+            .min_by_key(|&pos| self._distance_squared(pos))
     }
 
     fn _is_retreating(&self, plan: &Plan) -> bool {
@@ -572,7 +1507,7 @@ fn _load_from_csv(kind: u8, config: &str) -> Result<ActorStats, Box<dyn Error>>
         if let Some(actor_csv) = actor_cache.get(&kind) {
             return Ok(actor_csv.clone());
         }
-        for record in csv::Reader::from_path(&config)?.deserialize() {
+        for record in csv::Reader::from_path(config)?.deserialize() {
             let row: ActorStats = record?;
             actor_cache.insert(row.0, row.clone());
             if row.0 == kind {
@@ -583,9 +1518,25 @@ fn _load_from_csv(kind: u8, config: &str) -> Result<ActorStats, Box<dyn Error>>
     })
 }
 
+fn _distance_squared(from: (u16, u16), to: (u16, u16)) -> i32 {
+    let (dx, dy) = (
+        i32::from(from.0) - i32::from(to.0),
+        i32::from(from.1) - i32::from(to.1),
+    );
+    dx * dx + dy * dy
+}
+
+// Movement lag after speed bonuses, slowed by anything carried past capacity.
+fn _lag(move_lag: u16, speed: u16, capacity: u16, load: u16) -> u16 {
+    let excess = load.saturating_sub(capacity);
+    cmp::max(1, move_lag * 100 / (100 + speed)) + excess.div_ceil(2)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use constants::{ITEM_CHEST, ITEM_CHEST_LOCKED, ITEM_CHEST_OPEN, ITEM_KEY};
+    use controller::Human;
     use std::collections::HashSet;
     use std::iter::FromIterator;
 
@@ -598,7 +1549,7 @@ mod tests {
         let mut world = World::new("glad");
         world.reshape(wld_shape);
         world.add_item(boots, (2, 2));
-        return (soldier, archer, world, plan);
+        (soldier, archer, world, plan)
     }
 
     #[test]
@@ -611,12 +1562,12 @@ mod tests {
 
     #[test]
     fn test_predicates() {
-        let (soldier, archer, _world, _plan) = fixtures();
+        let (soldier, archer, _world, plan) = fixtures();
         assert!(soldier.is_alive());
         assert!(soldier.is_flesh());
         assert!(soldier.is_mobile());
         assert!(soldier.is_playable());
-        assert!(soldier.is_enemy_of(archer.team));
+        assert!(soldier.is_enemy_of(archer.team, &plan));
         assert!(soldier.is_combatant() && soldier._is_blocking(soldier.pos));
         assert!(!soldier.is_hurt());
         assert!(!soldier.is_artificial());
@@ -632,13 +1583,41 @@ mod tests {
         assert!(!soldier.is_near((100, 100)));
     }
 
+    #[test]
+    fn test_can_see() {
+        let (soldier, mut archer, mut world, _plan) = fixtures();
+        assert!(soldier.can_see(&archer, &world));
+        archer.pos = (0, 2);
+        world.tiles[11] = 0; // a wall at (1, 2)
+        assert!(!soldier.can_see(&archer, &world));
+        archer.pos = (1, 1);
+        archer.invis = 10;
+        assert!(soldier.can_see(&archer, &world));
+        archer.pos = (4, 4);
+        assert!(!soldier.can_see(&archer, &world));
+    }
+
+    #[test]
+    fn test_choose() {
+        let (soldier, mut archer, world, mut plan) = fixtures();
+        archer.direction = 2;
+        let mut actors = vec![soldier, archer];
+        plan.fast_update(&actors);
+        // shooting the soldier is worth more than hitting it:
+        assert_eq!(actors[1].choose(&world, &plan), ACT_SKILL);
+        assert_eq!(actors[1].selected_skill(), "shoot");
+        // ...unless the archer is out of mana:
+        actors[1].mana = 0;
+        assert_eq!(actors[1].choose(&world, &plan), 2);
+    }
+
     #[test]
     fn test_enemy_interactions() {
-        let (mut soldier, mut archer, mut world, plan) = fixtures();
+        let (mut soldier, mut archer, mut world, mut plan) = fixtures();
         let all_but_2 = soldier.health - 2;
         soldier.hurt(all_but_2, &mut world);
         assert!(soldier.is_alive() && soldier.is_hurt());
-        archer._act_touch(&mut soldier, &mut world, 2, &plan);
+        archer._act_touch(&mut soldier, &mut world, 2, &mut plan);
         assert!(soldier.health < all_but_2);
         archer._act_hit(&mut soldier, &mut world);
         assert!(!soldier.is_alive());
@@ -672,15 +1651,366 @@ mod tests {
 
     #[test]
     fn test_get_and_drop() {
-        let (mut soldier, _archer, mut world, plan) = fixtures();
+        let (mut soldier, _archer, mut world, mut plan) = fixtures();
         soldier.direction = 2;
         soldier._act_get_all(&mut world);
         assert_eq!(soldier.inventory.len(), 1);
         soldier._act_drop_all(&mut world);
         assert_eq!(soldier.inventory.len(), 0);
         // move forward and wait for auto-pickup:
-        soldier._act_move(2, &mut world, &plan, &mut vec![]);
+        soldier._act_move(2, &mut world, &mut plan, &mut vec![]);
         soldier.update(&mut world);
         assert_eq!(soldier.inventory.len(), 1);
     }
+
+    #[test]
+    fn test_statuses() {
+        let (mut soldier, mut archer, mut world, mut plan) = fixtures();
+        let health = soldier.health;
+        soldier.afflict(StatusKind::Poisoned, 3);
+        soldier.afflict(StatusKind::Poisoned, 1);
+        soldier.update(&mut world);
+        assert_eq!(soldier.health, health - 2);
+        for _ii in 0..2 {
+            soldier.update(&mut world);
+        }
+        assert!(!soldier.has_status(StatusKind::Poisoned));
+        // haste and slowness cancel each other out:
+        let move_lag = soldier.move_lag();
+        soldier.afflict(StatusKind::Hasted, 5);
+        assert!(soldier.move_lag() < move_lag);
+        soldier.afflict(StatusKind::Slowed, 5);
+        assert_eq!(soldier.move_lag(), move_lag);
+        // shields halve damage, roots stop movement:
+        let health = soldier.health;
+        soldier.afflict(StatusKind::Shielded, 5);
+        soldier.hurt(10, &mut world);
+        assert_eq!(soldier.health, health - 5);
+        archer.afflict(StatusKind::Rooted, 5);
+        archer._act_move(0, &mut world, &mut plan, &mut vec![]);
+        assert_eq!(archer.pos, (1, 2));
+    }
+
+    #[test]
+    fn test_level_up() {
+        let (mut soldier, _archer, _world, _plan) = fixtures();
+        soldier.level_up(None);
+        assert!(soldier.has_skill("sprint"));
+        soldier.level_up(Some("leap"));
+        assert!(soldier.has_skill("leap") && !soldier.has_skill("cleave"));
+        // learned skills survive reinitializing, and replace older ones:
+        soldier.initialize(0);
+        assert!(soldier.has_skill("leap"));
+        let mut mage = Actor::new(3, 5, 0, (0, 0));
+        assert!(!mage.has_skill("fireball") && mage.has_skill("blast"));
+        mage.level_up(None);
+        assert_eq!(mage.level, 6);
+        assert!(mage.has_skill("fireball") && !mage.has_skill("blast"));
+    }
+
+    #[test]
+    fn test_rest() {
+        let (mut soldier, _archer, _world, _plan) = fixtures();
+        let max = soldier.max_health();
+        soldier.health = 2;
+        soldier.mana = 0;
+        // resting between battles heals only half the wounds, leaving the
+        // rest for the shop's healer, but brings all the mana back:
+        soldier.rest();
+        assert_eq!(soldier.health, 2 + (max - 2) / 2);
+        assert_eq!(soldier.mana, soldier.max_mana());
+        soldier.health = 0;
+        soldier.rest();
+        assert!(!soldier.is_alive());
+    }
+
+    #[test]
+    fn test_level_up_keeps_stats() {
+        let (mut soldier, _archer, _world, _plan) = fixtures();
+        soldier.strength += 2;
+        soldier.name = "Gus".to_owned();
+        let (strength, con, intel) = (soldier.strength, soldier.con, soldier.intel);
+        soldier.level_up(None);
+        assert_eq!(
+            (soldier.strength, soldier.con, soldier.intel),
+            (strength, con, intel)
+        );
+        assert_eq!(soldier.name, "Gus");
+        assert!(soldier.has_skill("sprint"));
+    }
+
+    #[test]
+    fn test_reactions() {
+        let (soldier, archer, mut world, mut plan) = fixtures();
+        // leaving an enemy's side provokes a free attack:
+        let mut actors = vec![soldier, archer];
+        plan.fast_update(&actors);
+        let (soldier, archer) = actors.split_at_mut(1);
+        let health = soldier[0].health;
+        soldier[0]._act_move(2, &mut world, &mut plan, &mut vec![archer]);
+        assert_eq!(soldier[0].pos, (3, 2));
+        assert!(soldier[0].health < health);
+        // blockers facing an attacker block on a good roll, but never from behind:
+        let (mut soldier, mut archer, mut world, _plan) = fixtures();
+        soldier.direction = 6;
+        assert!(soldier._parry_with_roll(&mut archer, 2, 0));
+        assert!(!soldier._parry_with_roll(&mut archer, 2, 99));
+        soldier.direction = 2;
+        assert!(!soldier._parry_with_roll(&mut archer, 2, 0));
+        // the rest parry as often as their kind allows:
+        let mut orc = Actor::new(14, 1, 1, (2, 2));
+        orc.direction = 6;
+        assert!(orc._parry_with_roll(&mut archer, 2, orc.parry - 1));
+        assert!(!orc._parry_with_roll(&mut archer, 2, orc.parry));
+        let mut blob = Actor::new(8, 1, 1, (2, 2));
+        blob.direction = 6;
+        assert!(!blob._parry_with_roll(&mut archer, 2, 0));
+        // some kinds strike back when hit:
+        let mut barbarian = Actor::new(16, 1, 0, (2, 2));
+        let health = archer.health;
+        passive_counter(&mut barbarian, &mut archer, &mut world);
+        assert!(archer.health < health);
+    }
+
+    #[test]
+    fn test_summons_follow_orders() {
+        let (soldier, archer, world, mut plan) = fixtures();
+        let quarry = soldier.nearest_enemy_in_sight(std::slice::from_ref(&archer), &world, &plan);
+        assert_eq!(quarry, Some(archer.pos));
+        let mut faerie = Actor::new(7, 1, 0, (2, 2));
+        faerie.leash = Some(soldier.pos);
+        faerie.quarry = Some((4, 2));
+        plan.tactic_attack();
+        assert!(faerie._value_of_pos((3, 2), &plan) > faerie._value_of_pos((1, 2), &plan));
+        // while their side follows, summons keep to their summoner's heel:
+        faerie.leash = Some((0, 0));
+        faerie.pos = (3, 3);
+        plan.tactic_follow();
+        assert!(faerie._value_of_pos((2, 2), &plan) > faerie._value_of_pos((4, 4), &plan));
+    }
+
+    #[test]
+    fn test_death_loot() {
+        let (_soldier, _archer, mut world, _plan) = fixtures();
+        let items = world.items.len();
+        for _ii in 0..20 {
+            let mut tower = Actor::new(31, 1, 1, (2, 2));
+            tower.act_die(&mut world);
+        }
+        assert_eq!(world.items.len(), items);
+    }
+
+    #[test]
+    fn test_projectiles() {
+        let (mut soldier, mut archer, mut world, mut plan) = fixtures();
+        // arrows drop after hitting, crediting whoever fired them:
+        plan.fast_update(std::slice::from_ref(&archer));
+        let mut arrow = Actor::new(50, 1, 0, soldier.pos);
+        arrow.owner = Some(soldier.uid);
+        arrow.direction = 6;
+        let health = archer.health;
+        let mut targets = vec![std::slice::from_mut(&mut archer)];
+        arrow.act(6, &mut world, &mut plan, &mut targets, &mut vec![]);
+        assert!(!arrow.is_alive() && archer.health < health);
+        assert_eq!(world.credits[0].0, soldier.uid);
+        // boomerangs fly out of range, then come back to be caught:
+        let mut boomerang = Actor::new(53, 1, 0, soldier.pos);
+        boomerang.owner = Some(soldier.uid);
+        boomerang.direction = 2;
+        plan.fast_update(&[]);
+        for _turn in 0..20 {
+            let mut thrower = vec![std::slice::from_mut(&mut soldier)];
+            boomerang.act(2, &mut world, &mut plan, &mut thrower, &mut vec![]);
+        }
+        assert!(!boomerang.is_alive());
+        assert!(soldier
+            .log
+            .iter()
+            .any(|log| log.1 == "I caught a boomerang."));
+        // nobody is credited for what stray projectiles kill:
+        let credits = world.credits.len();
+        let mut stray = Actor::new(50, 1, 1, (2, 2));
+        let mut victim = Actor::new(7, 1, 0, (1, 2));
+        victim.health = 1;
+        stray._act_hit(&mut victim, &mut world);
+        assert!(!victim.is_alive());
+        assert_eq!((stray.kills, world.credits.len()), (0, credits));
+    }
+
+    #[test]
+    fn test_equipment() {
+        let (mut soldier, _archer, mut world, _plan) = fixtures();
+        let max_health = soldier.max_health();
+        soldier.add_to_inventory(Item::new(6, 1, 0));
+        soldier.add_to_inventory(Item::new(6, 1, 0));
+        assert_eq!(soldier._equipped().count(), 1);
+        assert!(soldier.max_health() > max_health);
+        // boots let their wearer wade through water:
+        assert!(soldier.walls().contains('~'));
+        soldier.add_to_inventory(Item::new(7, 1, 0));
+        assert!(!soldier.walls().contains('~'));
+        // dropping equipment leaves no trace on the stats:
+        soldier._act_drop_all(&mut world);
+        assert_eq!(soldier.max_health(), max_health);
+        assert!(soldier.walls().contains('~'));
+    }
+
+    #[test]
+    fn test_consumables() {
+        let (mut soldier, mut archer, mut world, mut plan) = fixtures();
+        soldier.add_to_inventory(Item::new(1, 1, 0));
+        soldier.add_to_inventory(Item::new(5, 1, 0));
+        assert_eq!(soldier.inventory.len(), 2);
+        // the AI eats when it's hurt:
+        let all_but_1 = soldier.health - 1;
+        soldier.hurt(all_but_1, &mut world);
+        assert_eq!(soldier.choose(&world, &plan), ACT_USE);
+        soldier.act(ACT_USE, &mut world, &mut plan, &mut vec![], &mut vec![]);
+        assert_eq!(soldier.health, soldier.max_health());
+        // thrown potions work on whoever they hit:
+        plan.fast_update(std::slice::from_ref(&archer));
+        soldier.direction = 6;
+        soldier.chosen_item = Some(0);
+        let mut targets = vec![std::slice::from_mut(&mut archer)];
+        soldier.act(ACT_THROW, &mut world, &mut plan, &mut targets, &mut vec![]);
+        assert!(soldier.inventory.is_empty() && archer.invis > 0);
+    }
+
+    #[test]
+    fn test_inventory_actions() {
+        let (mut soldier, _archer, mut world, mut plan) = fixtures();
+        soldier.add_to_inventory(Item::new(6, 1, 0));
+        soldier.add_to_inventory(Item::new(4, 1, 0));
+        // items are sorted by kind, so the ring comes before the armor:
+        let preview = soldier.stat_preview(1, ACT_DROP);
+        assert_eq!(preview.len(), 1);
+        assert!(preview[0].0 == "health" && preview[0].1 > preview[0].2);
+        assert_eq!(soldier.stat_preview(0, ACT_USE)[0].0, "mana");
+        // the chosen item goes to the teammate alongside:
+        let mut teammate = Actor::new(2, 1, 0, (1, 2));
+        soldier.chosen_item = Some(1);
+        let mut teammates = vec![std::slice::from_mut(&mut teammate)];
+        soldier.act(ACT_GIVE, &mut world, &mut plan, &mut teammates, &mut vec![]);
+        assert_eq!(soldier.inventory.len(), 1);
+        assert!(teammate.inventory[0].is_equipped);
+        // trading swaps the chosen item for the wanted one:
+        soldier.chosen_item = Some(0);
+        soldier.wanted_item = Some(0);
+        soldier.trade_partner = Some(teammate.uid);
+        let mut teammates = vec![std::slice::from_mut(&mut teammate)];
+        soldier.act(
+            ACT_TRADE,
+            &mut world,
+            &mut plan,
+            &mut teammates,
+            &mut vec![],
+        );
+        assert_eq!(soldier.inventory[0].kind, 6);
+        assert_eq!(teammate.inventory[0].kind, 4);
+        // only with the teammate it picked, though:
+        soldier.chosen_item = Some(0);
+        soldier.wanted_item = Some(0);
+        soldier.trade_partner = Some(teammate.uid + 1);
+        let mut teammates = vec![std::slice::from_mut(&mut teammate)];
+        soldier.act(
+            ACT_TRADE,
+            &mut world,
+            &mut plan,
+            &mut teammates,
+            &mut vec![],
+        );
+        assert_eq!(soldier.inventory[0].kind, 6);
+        assert_eq!(soldier.wanted_item, None);
+        assert!(soldier.is_trade_partner(&teammate) && !soldier.is_trade_partner(&soldier));
+        soldier.chosen_item = Some(0);
+        soldier._act_drop_item(&mut world);
+        assert!(soldier.inventory.is_empty());
+    }
+
+    #[test]
+    fn test_encumbrance() {
+        let (mut soldier, _archer, mut world, plan) = fixtures();
+        let move_lag = soldier.move_lag();
+        while soldier.load() <= soldier.capacity() {
+            soldier.add_to_inventory(Item::new(6, 1, 0));
+        }
+        assert!(soldier.move_lag() > move_lag);
+        // the AI leaves behind what it can't carry:
+        assert_eq!(soldier.choose(&world, &plan), ACT_DROP);
+        world.add_item(Item::new(6, 1, 0), soldier.pos);
+        let load = soldier.load();
+        soldier._act_get_all(&mut world);
+        assert_eq!(soldier.load(), load);
+        // but a human player may carry as much as they like, leader or not:
+        soldier.controller = Box::new(Human);
+        soldier._act_get_all(&mut world);
+        assert!(soldier.load() > load);
+        assert_eq!(soldier._choose_item_to_drop(), None);
+    }
+
+    #[test]
+    fn test_containers() {
+        let (mut soldier, _archer, mut world, plan) = fixtures();
+        let mut chest = Item::new(ITEM_CHEST_LOCKED, 1, 0);
+        chest.contents.push(Item::new(13, 1, 0));
+        chest.contents.push(Item::new(6, 1, 0));
+        world.add_item(chest, (3, 2));
+        assert_eq!(
+            world.neighbor(soldier.pos, 2, soldier.team, ""),
+            soldier.pos
+        );
+        soldier._act_push_wall(&mut world, 2);
+        assert_eq!(soldier.inventory.len(), 0);
+        // a key unlocks it, and the soldier takes only what it can carry:
+        soldier.add_to_inventory(Item::new(ITEM_KEY, 1, 0));
+        soldier.strength = 1;
+        soldier._act_push_wall(&mut world, 2);
+        let idx = world.container_at((3, 2)).unwrap();
+        assert_eq!(world.items[idx].kind, ITEM_CHEST_OPEN);
+        assert_eq!(soldier.inventory.len(), 2);
+        assert_eq!(world.items[idx].contents.len(), 1);
+        // players only look inside at first, then take what they pick:
+        let mut player = Actor::new(0, 1, 0, (2, 2));
+        player.controller = Box::new(Human);
+        world.items[idx].contents.push(Item::new(15, 1, 0));
+        player._act_push_wall(&mut world, 2);
+        assert!(player.inventory.is_empty());
+        player.wanted_item = Some(1);
+        player._act_push_wall(&mut world, 2);
+        assert_eq!(player.inventory[0].kind, 15);
+        assert_eq!(world.items[idx].contents.len(), 1);
+        assert_eq!(player.wanted_item, None);
+        // thieves need no key:
+        let mut thief = Actor::new(11, 1, 0, (2, 2));
+        thief.direction = 2;
+        thief.mana = thief.max_mana();
+        world.items[idx].initialize_as(ITEM_CHEST_LOCKED);
+        pick(&mut thief, &mut world, &plan, &mut Vec::new());
+        assert_eq!(world.items[idx].kind, ITEM_CHEST);
+    }
+
+    #[test]
+    fn test_life_gems() {
+        let (mut soldier, mut archer, mut world, mut plan) = fixtures();
+        archer.team = 0;
+        soldier.is_persistent = true;
+        soldier.act_die(&mut world);
+        let corpse = world.items.iter().find(|item| item.kind == ITEM_CORPSE);
+        assert_eq!(corpse.unwrap().owner, Some(soldier.uid));
+        // a teammate alongside with a gem uses it on the corpse:
+        archer.add_to_inventory(Item::new(ITEM_LIFE_GEM, 1, 0));
+        plan.fast_update(std::slice::from_ref(&archer));
+        assert_eq!(archer.choose(&world, &plan), ACT_USE);
+        archer.act(ACT_USE, &mut world, &mut plan, &mut vec![], &mut vec![]);
+        assert!(archer.inventory.is_empty());
+        assert_eq!(world.revivals, vec![(soldier.uid, (2, 2))]);
+        assert!(!world.items.iter().any(|item| item.kind == ITEM_CORPSE));
+        soldier.revive((2, 2));
+        assert!(soldier.is_alive() && soldier.health < soldier.max_health());
+        // with no corpse around, the gem is kept:
+        archer.add_to_inventory(Item::new(ITEM_LIFE_GEM, 1, 0));
+        archer.chosen_item = Some(0);
+        archer.act(ACT_USE, &mut world, &mut plan, &mut vec![], &mut vec![]);
+        assert_eq!(archer.inventory.len(), 1);
+    }
 }
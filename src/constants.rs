@@ -1,17 +1,49 @@
 pub const ACT_SKILL: u8 = 30;
+pub const ACT_CANCEL: u8 = 73;
 pub const ACT_DROP: u8 = 40;
+pub const ACT_USE: u8 = 42;
+pub const ACT_THROW: u8 = 43;
+pub const ACT_GIVE: u8 = 44;
+pub const ACT_TRADE: u8 = 45;
 pub const ACT_WAIT: u8 = 8;
 pub const ACT_MOVES: [u8; 9] = [0, 1, 2, 3, 4, 5, 6, 7, ACT_WAIT];
 pub const ACT_TURNS: [u8; 8] = [16, 17, 18, 19, 20, 21, 22, 23];
 
+pub const ITEM_CORPSE: u8 = 0;
+pub const ITEM_GOLD: u8 = 2;
+pub const ITEM_SILVER: u8 = 3;
 pub const ITEM_EXIT: u8 = 8;
 pub const ITEM_PORTAL: u8 = 9;
+pub const ITEM_LIFE_GEM: u8 = 10;
 pub const ITEM_KEY: u8 = 11;
+pub const ITEM_HEALING: u8 = 17;
 pub const ITEM_DOOR: u8 = 18;
 pub const ITEM_DOOR_OPEN: u8 = 19;
+pub const ITEM_CHEST: u8 = 20;
+pub const ITEM_CHEST_LOCKED: u8 = 21;
+pub const ITEM_CHEST_OPEN: u8 = 22;
+pub const ITEM_RESURRECTION: u8 = 23;
 pub const ITEM_TELEPORT_MARKER: u8 = 50;
 pub const ITEM_TREE: u8 = 100;
 pub const ITEM_DEBRIS: u8 = 200;
 
+pub const SLOT_NONE: u8 = 0;
+pub const SLOT_WEAPON: u8 = 1;
+pub const SLOT_ARMOR: u8 = 2;
+pub const SLOT_RING: u8 = 3;
+pub const SLOT_AMULET: u8 = 4;
+pub const SLOT_BOOTS: u8 = 5;
+
 pub const TILE_TREE: u16 = 60;
 pub const TILE_BLOOD: u16 = 200;
+pub const TILE_SCORCHED: u16 = 79;
+pub const TILE_MARSH: [u16; 4] = [47, 48, 49, 50];
+
+pub const TARGET_NONE: u8 = 0;
+pub const TARGET_DIRECTION: u8 = 1;
+pub const TARGET_CELL: u8 = 2;
+pub const TARGET_ALLY: u8 = 3;
+
+pub const EFFECT_EXPLOSION: u8 = 0;
+pub const EFFECT_CLOUD: u8 = 1;
+pub const EFFECT_CONE: u8 = 2;
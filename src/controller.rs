@@ -0,0 +1,127 @@
+// Handles who decides an actor's moves: the keyboard, the AI, or a script.
+use actor::Actor;
+use constants::ACT_WAIT;
+use plan::Plan;
+use std::collections::VecDeque;
+use std::sync::mpsc::Receiver;
+use world::World;
+
+pub trait Controller {
+    /// The actor's next action, or None to have the player pick one.
+    fn choose(&mut self, actor: &mut Actor, world: &World, plan: &Plan) -> Option<u8>;
+
+    /// Whether one of the players steers the actor from the keyboard.
+    fn is_human(&self) -> bool {
+        false
+    }
+
+    /// Whether the actor's own AI decides, so a player may take it over.
+    fn is_ai(&self) -> bool {
+        false
+    }
+}
+
+/// Reads the actor's moves from the keyboard.
+pub struct Human;
+
+impl Controller for Human {
+    fn choose(&mut self, _actor: &mut Actor, _world: &World, _plan: &Plan) -> Option<u8> {
+        None
+    }
+
+    fn is_human(&self) -> bool {
+        true
+    }
+}
+
+/// Lets the actor's own tactics and skills decide.
+pub struct Ai;
+
+impl Controller for Ai {
+    fn choose(&mut self, actor: &mut Actor, world: &World, plan: &Plan) -> Option<u8> {
+        Some(actor.choose(world, plan))
+    }
+
+    fn is_ai(&self) -> bool {
+        true
+    }
+}
+
+/// Plays back a fixed list of actions, then waits.
+pub struct Scripted {
+    actions: VecDeque<u8>,
+}
+
+impl Scripted {
+    pub fn new(actions: Vec<u8>) -> Scripted {
+        Scripted {
+            actions: VecDeque::from(actions),
+        }
+    }
+}
+
+impl Controller for Scripted {
+    fn choose(&mut self, _actor: &mut Actor, _world: &World, _plan: &Plan) -> Option<u8> {
+        Some(self.actions.pop_front().unwrap_or(ACT_WAIT))
+    }
+}
+
+/// Takes actions sent from elsewhere, e.g. another thread; waits when none arrive.
+pub struct External {
+    actions: Receiver<u8>,
+}
+
+impl External {
+    pub fn new(actions: Receiver<u8>) -> External {
+        External { actions }
+    }
+}
+
+impl Controller for External {
+    fn choose(&mut self, _actor: &mut Actor, _world: &World, _plan: &Plan) -> Option<u8> {
+        Some(self.actions.try_recv().unwrap_or(ACT_WAIT))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::iter::FromIterator;
+    use std::sync::mpsc::channel;
+
+    fn fixtures() -> (Actor, World, Plan) {
+        let mut world = World::new("glad");
+        world.reshape((5, 5));
+        let plan = Plan::new((5, 5), &HashSet::from_iter(vec![0]));
+        (Actor::new(0, 1, 0, (2, 2)), world, plan)
+    }
+
+    #[test]
+    fn test_human_and_ai() {
+        let (mut actor, world, plan) = fixtures();
+        assert!(Human.choose(&mut actor, &world, &plan).is_none());
+        assert!(Ai.choose(&mut actor, &world, &plan).is_some());
+        assert!(Human.is_human() && !Ai.is_human());
+        assert!(Ai.is_ai() && !Human.is_ai() && !Scripted::new(vec![]).is_ai());
+    }
+
+    #[test]
+    fn test_scripted() {
+        let (mut actor, world, plan) = fixtures();
+        let mut script = Scripted::new(vec![2, 4]);
+        assert_eq!(script.choose(&mut actor, &world, &plan), Some(2));
+        assert_eq!(script.choose(&mut actor, &world, &plan), Some(4));
+        assert_eq!(script.choose(&mut actor, &world, &plan), Some(ACT_WAIT));
+    }
+
+    #[test]
+    fn test_external() {
+        let (mut actor, world, plan) = fixtures();
+        let (sender, receiver) = channel();
+        let mut external = External::new(receiver);
+        sender.send(6).unwrap();
+        assert_eq!(external.choose(&mut actor, &world, &plan), Some(6));
+        assert_eq!(external.choose(&mut actor, &world, &plan), Some(ACT_WAIT));
+    }
+}
@@ -0,0 +1,175 @@
+// Handles area effects like explosions and clouds that hurt all in their way.
+use actor::Actor;
+use constants::{EFFECT_CLOUD, EFFECT_CONE, EFFECT_EXPLOSION};
+use ncurses::*;
+use status::StatusKind;
+use world::World;
+
+pub struct Effect {
+    pub kind: u8,
+    pub pos: (u16, u16),
+    pub direction: u8, // for cones
+    pub radius: u16,
+    pub damage: u16,
+    pub turns: u16,           // world ticks the effect stays around
+    pub owner: Option<usize>, // uid of whoever caused it, credited with its kills
+    age: u16,
+}
+
+impl Effect {
+    pub fn new(kind: u8, pos: (u16, u16), radius: u16, damage: u16) -> Effect {
+        Effect {
+            kind,
+            pos,
+            radius,
+            damage,
+            direction: 0,
+            turns: 1,
+            owner: None,
+            age: 0,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self.kind {
+            EFFECT_EXPLOSION => "an explosion",
+            EFFECT_CLOUD => "a noxious cloud",
+            _ => "a sweeping blow",
+        }
+    }
+
+    pub fn color(&self) -> i16 {
+        match self.kind {
+            EFFECT_EXPLOSION => COLOR_RED,
+            EFFECT_CLOUD => COLOR_GREEN,
+            _ => COLOR_YELLOW,
+        }
+    }
+
+    /// Clouds linger, hurting those inside on each of their turns, as statuses do.
+    pub fn is_lingering(&self) -> bool {
+        self.kind == EFFECT_CLOUD
+    }
+
+    /// The rest hurt everyone in the area once, when they appear.
+    pub fn is_harmful(&self) -> bool {
+        !self.is_lingering() && self.age == 0
+    }
+
+    /// Hurts an actor caught in the effect, telling its owner how it went.
+    pub fn hurt(&self, actor: &mut Actor, world: &mut World) {
+        actor.log_action(&format!("was caught in {}.", self.name()));
+        actor.hurt(self.damage_at(actor.pos), world);
+        match self.kind {
+            EFFECT_EXPLOSION => actor.afflict(StatusKind::Burning, 3),
+            EFFECT_CLOUD => actor.afflict(StatusKind::Poisoned, 2),
+            _ => {}
+        }
+        match self.owner {
+            Some(owner) if owner != actor.uid => {
+                let killed = !actor.is_alive();
+                let verb = if killed { "killed" } else { "caught" };
+                let txt = format!("{} {} in {}.", verb, actor.name, self.name());
+                world.credit(owner, &txt, killed);
+            }
+            _ => {}
+        }
+    }
+
+    /// Ages the effect by a turn, returning whether it is still around.
+    pub fn tick(&mut self) -> bool {
+        self.age += 1;
+        self.age <= self.turns
+    }
+
+    /// Cells within the radius that the effect can reach from its center.
+    pub fn cells(&self, world: &World) -> Vec<(u16, u16)> {
+        let mut cells = Vec::new();
+        let radius = i32::from(self.radius);
+        let (x, y) = (i32::from(self.pos.0), i32::from(self.pos.1));
+        for yy in y - radius..=y + radius {
+            for xx in x - radius..=x + radius {
+                if world.is_out_of_bounds((xx as i16, yy as i16)) {
+                    continue;
+                }
+                let pos = (xx as u16, yy as u16);
+                let in_area = match self.kind {
+                    EFFECT_CONE => self._is_in_cone(pos),
+                    _ => true,
+                };
+                if in_area && world.is_in_view(self.pos, pos) {
+                    cells.push(pos);
+                }
+            }
+        }
+        cells
+    }
+
+    // Cones spread out up to 45 degrees either side of their direction.
+    fn _is_in_cone(&self, pos: (u16, u16)) -> bool {
+        let dir = match self.direction {
+            0 => (0, -1),
+            1 => (1, -1),
+            2 => (1, 0),
+            3 => (1, 1),
+            4 => (0, 1),
+            5 => (-1, 1),
+            6 => (-1, 0),
+            _ => (-1, -1),
+        };
+        let to = (
+            i32::from(pos.0) - i32::from(self.pos.0),
+            i32::from(pos.1) - i32::from(self.pos.1),
+        );
+        let (dot, cross) = (dir.0 * to.0 + dir.1 * to.1, dir.0 * to.1 - dir.1 * to.0);
+        dot > 0 && cross.abs() <= dot
+    }
+
+    /// Explosions are weaker further from their center.
+    pub fn damage_at(&self, pos: (u16, u16)) -> u16 {
+        if self.kind != EFFECT_EXPLOSION {
+            return self.damage;
+        }
+        let dist = (i32::from(pos.0) - i32::from(self.pos.0))
+            .abs()
+            .max((i32::from(pos.1) - i32::from(self.pos.1)).abs()) as u16;
+        self.damage * (self.radius + 1 - dist.min(self.radius)) / (self.radius + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cells_and_damage() {
+        let mut world = World::new("glad");
+        world.reshape((5, 5));
+        let explosion = Effect::new(EFFECT_EXPLOSION, (2, 2), 1, 10);
+        assert_eq!(explosion.cells(&world).len(), 9);
+        assert_eq!(explosion.damage_at((2, 2)), 10);
+        assert_eq!(explosion.damage_at((1, 1)), 5);
+        let mut cone = Effect::new(EFFECT_CONE, (2, 2), 2, 10);
+        cone.direction = 2;
+        assert!(cone.cells(&world).contains(&(4, 2)) && cone.cells(&world).contains(&(4, 0)));
+        assert!(!cone.cells(&world).contains(&(2, 2)) && !cone.cells(&world).contains(&(1, 2)));
+        let mut cloud = Effect::new(EFFECT_CLOUD, (2, 2), 1, 1);
+        cloud.turns = 2;
+        assert!(cloud.is_lingering() && !cloud.is_harmful());
+        assert!(cloud.tick() && cloud.tick() && !cloud.tick());
+    }
+
+    #[test]
+    fn test_hurt_and_credit() {
+        let mut world = World::new("glad");
+        world.reshape((5, 5));
+        let mut explosion = Effect::new(EFFECT_EXPLOSION, (2, 2), 1, 1000);
+        explosion.owner = Some(42);
+        let mut soldier = Actor::new(0, 1, 1, (2, 2));
+        explosion.hurt(&mut soldier, &mut world);
+        assert!(!soldier.is_alive());
+        assert_eq!(world.credits.len(), 1);
+        assert_eq!(world.credits[0].0, 42);
+        assert!(world.credits[0].2);
+    }
+}
@@ -1,9 +1,11 @@
 // Handles loading of game assets from the original Gladiator 3.8 sources.
 use actor::Actor;
-use constants::{ITEM_EXIT, ITEM_PORTAL};
+use constants::{ITEM_EXIT, ITEM_GOLD, ITEM_PORTAL, ITEM_SILVER};
 use inflector::Inflector;
 use item::Item;
+use loot;
 use rand;
+use rand::Rng;
 use state::State;
 use std::fs::File;
 use std::io::prelude::*;
@@ -22,6 +24,7 @@ const ORD_ITEM_OR_EXIT: u8 = 2;
 const ORD_GENERATOR: u8 = 3;
 const ORD_EFFECT: u8 = 4;
 const ORD_SPAWN: u8 = 5;
+const TREASURE_BLOCKED_BY: &str = "'^%#`*~"; // anywhere a soldier can't walk
 
 // See: https://github.com/openglad/openglad/blob/master/src/base.h
 // NOTE: Will pop state.player_team into spawn locations.
@@ -52,6 +55,7 @@ pub fn load_world_and_spawn_team(state: &mut State) {
     }
 
     if !state.world_completed.contains(&state.world_idx) {
+        _hide_treasure(&mut state.world);
         let num_lines = _read_bytes(1, &mut file)[0];
         for _line in 0..num_lines {
             let num_chars = u64::from(_read_bytes(1, &mut file)[0]);
@@ -83,9 +87,35 @@ fn _load_world_layout(world: &mut World, pix: &str) {
     }
 }
 
+// Each pile of coins hints at treasure hidden somewhere else in the level.
+fn _hide_treasure(world: &mut World) {
+    let levels: Vec<u16> = world
+        .items
+        .iter()
+        .filter(|item| item.kind == ITEM_GOLD || item.kind == ITEM_SILVER)
+        .map(|item| item.level)
+        .collect();
+    let mut free_cells: Vec<(u16, u16)> = (0..world.size.1)
+        .flat_map(|y| (0..world.size.0).map(move |x| (x, y)))
+        .filter(|&pos| !TREASURE_BLOCKED_BY.contains(world.glyph_at(pos)))
+        .filter(|&pos| world.items.iter().all(|item| item.pos != pos))
+        .collect();
+    for level in levels {
+        if free_cells.is_empty() {
+            return;
+        }
+        if let Some(treasure) = loot::for_treasure(level) {
+            let idx = rand::thread_rng().gen_range(0, free_cells.len());
+            world.add_item(treasure, free_cells.swap_remove(idx));
+        }
+    }
+}
+
 pub fn create_random_team(team: usize, count: usize) -> Vec<Actor> {
     let mut actors = Vec::new();
-    for kind in rand::sample(&mut rand::thread_rng(), &[0, 1, 2, 3, 5, 11, 13], count) {
+    let mut kinds = [0, 1, 2, 3, 5, 11, 13];
+    rand::thread_rng().shuffle(&mut kinds);
+    for kind in kinds.iter().take(count) {
         actors.push(Actor::new(*kind, 1, team, (0, 0)));
     }
     actors
@@ -163,12 +193,9 @@ fn _give_random_inventory(actor: &mut Actor) {
     if actor.team == 0 || !actor.is_leader {
         return;
     }
-    let gold = Item::new(2, actor.level, actor.team);
-    let silver = Item::new(3, actor.level, actor.team);
-    let armor = Item::new(6, actor.level, actor.team);
-    actor.inventory.push(gold);
-    actor.inventory.push(silver);
-    actor.inventory.push(armor);
+    for item in loot::for_leader(actor.kind, actor.level, actor.team) {
+        actor.add_to_inventory(item);
+    }
 }
 
 fn _resolve_filename(archive: &mut zip::read::ZipArchive<File>, idx: usize) -> String {
@@ -194,8 +221,8 @@ fn _read_bytes(amt: u64, file: &mut zip::read::ZipFile) -> Vec<u8> {
 }
 
 // Interpret a c-style string with a nul terminator.
-fn _read_c_string(max_amt: u64, mut file: &mut zip::read::ZipFile) -> String {
-    let buffer = _read_bytes(max_amt, &mut file);
+fn _read_c_string(max_amt: u64, file: &mut zip::read::ZipFile) -> String {
+    let buffer = _read_bytes(max_amt, file);
     if let Some(strlen) = buffer.iter().position(|&byte| (byte as char) < ' ') {
         return str::from_utf8(&buffer[..strlen]).unwrap().to_owned();
     }
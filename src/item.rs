@@ -1,6 +1,9 @@
 // Handles inanimate objects like exits, potions, and treasure.
 use constants;
+use constants::{ITEM_CHEST, ITEM_CHEST_LOCKED, ITEM_CHEST_OPEN, ITEM_GOLD, ITEM_SILVER};
+use constants::{SLOT_AMULET, SLOT_ARMOR, SLOT_BOOTS, SLOT_NONE, SLOT_RING, SLOT_WEAPON};
 use csv;
+use item_effects;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::error::Error;
@@ -16,6 +19,18 @@ pub struct Item {
     pub can_get: bool,
     pub can_keep: bool,
     pub can_retain: bool,
+    pub slot: u8,
+    pub is_equipped: bool,
+    pub strength: u16, // bonuses while equipped
+    pub con: u16,
+    pub intel: u16,
+    pub speed: u16,     // percent faster movement
+    pub passes: String, // walls the wearer can pass through
+    pub price: u16,     // in silver pieces
+    pub weight: u16,
+    pub rarity: u8,           // index into RARITIES
+    pub contents: Vec<Item>,  // what a container holds
+    pub owner: Option<usize>, // uid of the teammate a corpse belonged to
     health: u16,
 }
 
@@ -27,8 +42,19 @@ type ItemStats = (
     bool,   // can_get
     bool,   // can_keep
     bool,   // can_retain
+    String, // slot
+    u16,    // strength
+    u16,    // con
+    u16,    // intel
+    u16,    // speed
+    String, // passes
+    u16,    // price
+    u16,    // weight
 );
 
+/// Rarer equipment is named for its tier and multiplies its bonuses.
+pub const RARITIES: [&str; 4] = ["", "fine", "rare", "legendary"];
+
 thread_local!(static _ITEM_CSV_CACHE: RefCell<HashMap<u8, ItemStats>> = RefCell::new(HashMap::new()));
 
 impl Item {
@@ -44,6 +70,18 @@ impl Item {
             can_get: false,
             can_keep: false,
             can_retain: false,
+            slot: SLOT_NONE,
+            is_equipped: false,
+            strength: 0,
+            con: 0,
+            intel: 0,
+            speed: 0,
+            passes: String::new(),
+            price: 0,
+            weight: 0,
+            rarity: 0,
+            contents: Vec::new(),
+            owner: None,
             health: 20,
         };
         item.initialize_as(kind);
@@ -59,6 +97,66 @@ impl Item {
         self.can_get = row.4;
         self.can_keep = row.5;
         self.can_retain = row.6;
+        self.slot = match row.7.as_str() {
+            "weapon" => SLOT_WEAPON,
+            "armor" => SLOT_ARMOR,
+            "ring" => SLOT_RING,
+            "amulet" => SLOT_AMULET,
+            "boots" => SLOT_BOOTS,
+            _ => SLOT_NONE,
+        };
+        self.strength = row.8;
+        self.con = row.9;
+        self.intel = row.10;
+        self.speed = row.11;
+        self.passes = row.12;
+        self.price = row.13;
+        self.weight = row.14;
+        self.rarity = 0;
+    }
+
+    /// Raises equipment to a rarity tier, scaling its bonuses and price.
+    pub fn set_rarity(&mut self, rarity: u8) {
+        if !self.is_equipment() || rarity == 0 {
+            return;
+        }
+        let rarity = rarity.min(RARITIES.len() as u8 - 1);
+        let scale = u16::from(rarity) + 1;
+        self.strength *= scale;
+        self.con *= scale;
+        self.intel *= scale;
+        self.speed *= scale;
+        self.price *= scale * scale;
+        self.rarity = rarity;
+        self.name = _with_adjective(&self.name, RARITIES[rarity as usize]);
+    }
+
+    /// What the item does, in a few words.
+    pub fn describe(&self) -> String {
+        let mut bonuses = Vec::new();
+        for &(bonus, stat) in &[
+            (self.strength, "str"),
+            (self.con, "con"),
+            (self.intel, "int"),
+        ] {
+            if bonus > 0 {
+                bonuses.push(format!("+{} {}", bonus, stat));
+            }
+        }
+        if self.speed > 0 {
+            bonuses.push(format!("{}% faster", self.speed));
+        }
+        if !self.passes.is_empty() {
+            bonuses.push(format!("crosses {}", self.passes));
+        }
+        if bonuses.is_empty() {
+            return item_effects::describe(self.kind);
+        }
+        bonuses.join(", ")
+    }
+
+    pub fn is_equipment(&self) -> bool {
+        self.slot != SLOT_NONE
     }
 
     pub fn damage(&mut self) {
@@ -69,18 +167,74 @@ impl Item {
         }
     }
 
+    pub fn is_container(&self) -> bool {
+        self.kind == ITEM_CHEST || self.kind == ITEM_CHEST_LOCKED || self.kind == ITEM_CHEST_OPEN
+    }
+
+    pub fn is_currency(&self) -> bool {
+        self.kind == ITEM_GOLD || self.kind == ITEM_SILVER
+    }
+
     pub fn is_debris(&self) -> bool {
         self.kind == constants::ITEM_DEBRIS
     }
 }
 
+/// Everything a shop can sell, cheapest first.
+pub fn wares() -> Vec<Item> {
+    let mut wares: Vec<Item> = _load_kinds("config/glad/item.csv")
+        .unwrap()
+        .into_iter()
+        .map(|kind| Item::new(kind, 1, 0))
+        .filter(|item| item.price > 0 && item.can_keep && !item.is_currency())
+        .collect();
+    wares.sort_by_key(|item| item.price);
+    wares
+}
+
+/// Shows an amount of silver as gold and silver pieces.
+pub fn format_price(silver: u32) -> String {
+    let price = Item::new(ITEM_GOLD, 1, 0).price;
+    let (gold, silver) = (silver / u32::from(price), silver % u32::from(price));
+    match (gold, silver) {
+        (0, silver) => format!("{}s", silver),
+        (gold, 0) => format!("{}g", gold),
+        (gold, silver) => format!("{}g {}s", gold, silver),
+    }
+}
+
+/// Slips an adjective in after the article, fixing "an" and "a" to suit.
+fn _with_adjective(name: &str, adjective: &str) -> String {
+    match name.split_once(' ') {
+        Some(("a", rest)) | Some(("an", rest)) => {
+            let article = if adjective.starts_with(['a', 'e', 'i', 'o', 'u']) {
+                "an"
+            } else {
+                "a"
+            };
+            format!("{} {} {}", article, adjective, rest)
+        }
+        Some((article, rest)) => format!("{} {} {}", article, adjective, rest),
+        None => format!("{} {}", adjective, name),
+    }
+}
+
+fn _load_kinds(config: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut kinds = Vec::new();
+    for record in csv::Reader::from_path(config)?.deserialize() {
+        let row: ItemStats = record?;
+        kinds.push(row.0);
+    }
+    Ok(kinds)
+}
+
 fn _load_from_csv(kind: u8, config: &str) -> Result<ItemStats, Box<dyn Error>> {
-    return _ITEM_CSV_CACHE.with(|item_cache_cell| {
+    _ITEM_CSV_CACHE.with(|item_cache_cell| {
         let mut item_cache = item_cache_cell.borrow_mut();
         if let Some(item_csv) = item_cache.get(&kind) {
             return Ok(item_csv.clone());
         }
-        for record in csv::Reader::from_path(&config)?.deserialize() {
+        for record in csv::Reader::from_path(config)?.deserialize() {
             let row: ItemStats = record?;
             item_cache.insert(row.0, row.clone());
             if row.0 == kind {
@@ -88,7 +242,7 @@ fn _load_from_csv(kind: u8, config: &str) -> Result<ItemStats, Box<dyn Error>> {
             }
         }
         panic!("Unable to load {} from {}", kind, config)
-    });
+    })
 }
 
 #[cfg(test)]
@@ -104,6 +258,51 @@ mod tests {
         assert!(!item.is_debris());
     }
 
+    #[test]
+    fn test_slots() {
+        let armor = Item::new(6, 1, 0);
+        assert_eq!(armor.slot, SLOT_ARMOR);
+        assert!(armor.is_equipment() && !armor.is_equipped);
+        assert!(!Item::new(1, 1, 0).is_equipment());
+    }
+
+    #[test]
+    fn test_describe() {
+        assert_eq!(Item::new(6, 1, 0).describe(), "+1 con");
+        assert_eq!(Item::new(7, 1, 0).describe(), "crosses ~");
+        assert!(!Item::new(1, 1, 0).describe().is_empty());
+    }
+
+    #[test]
+    fn test_rarity() {
+        let mut amulet = Item::new(12, 1, 0);
+        let price = amulet.price;
+        amulet.set_rarity(1);
+        assert_eq!(amulet.name, "a fine amulet of speed");
+        assert_eq!((amulet.speed, amulet.price), (200, price * 4));
+        let mut armor = Item::new(6, 1, 0);
+        armor.set_rarity(9);
+        assert_eq!(armor.name, "some legendary armor");
+        assert_eq!(armor.con, 4);
+        let mut sword = Item::new(13, 1, 0);
+        sword.set_rarity(2);
+        assert_eq!(sword.name, "a rare sword");
+        let mut meat = Item::new(1, 1, 0);
+        meat.set_rarity(2);
+        assert_eq!((meat.name.as_str(), meat.rarity), ("some meat - yum", 0));
+    }
+
+    #[test]
+    fn test_wares() {
+        let wares = wares();
+        assert!(!wares.is_empty());
+        assert!(wares
+            .iter()
+            .all(|item| !item.is_currency() && item.price > 0));
+        assert_eq!(format_price(23), "2g 3s");
+        assert_eq!(format_price(7), "7s");
+    }
+
     #[test]
     fn test_damage() {
         let mut item = Item::new(0, 1, 0);
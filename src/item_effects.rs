@@ -1,29 +1,164 @@
 // Handles basic item effects.
 use actor::Actor;
-use constants::{ITEM_DOOR, ITEM_DOOR_OPEN, ITEM_KEY, ITEM_PORTAL, ITEM_TREE};
+use constants::{ITEM_CHEST, ITEM_CHEST_LOCKED, ITEM_DOOR, ITEM_DOOR_OPEN};
+use constants::{ITEM_KEY, ITEM_LIFE_GEM, ITEM_PORTAL, ITEM_TREE};
+use csv;
 use item::Item;
+use status::{self, Status, StatusKind};
+use std::cell::RefCell;
+use std::cmp;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io;
+
+type ItemEffectRow = (
+    u8,     // item kind
+    String, // effect: heal, restore, cure, afflict or hide
+    String, // status name, for cure and afflict
+    u16,    // percent restored, or turns lasted
+);
+
+// An item effect, checked against the known effects and statuses as it loads.
+enum ItemEffect {
+    Heal(u16),
+    Restore(u16),
+    Cure(StatusKind),
+    Afflict(StatusKind, u16),
+    Hide(u16),
+}
+
+thread_local!(static _EFFECT_CSV_CACHE: RefCell<HashMap<u8, Vec<ItemEffect>>> = RefCell::new(HashMap::new()));
 
 pub fn use_on_actor(actor: &mut Actor, kind: u8) -> bool {
-    match kind {
-        1 => actor.health = actor.max_health(),
-        2 => {} // gold
-        3 => {} // silver
-        4 => actor.intel += 1,
-        5 => actor.invis = 200,
-        6 => actor.con += 1,
-        7 => actor.walls = actor.walls.replace("~", ""),
-        12 => actor.move_lag = (actor.move_lag / 2) + 1,
-        _ => return false,
+    _with_effects(kind, |effects| {
+        for effect in effects {
+            match *effect {
+                ItemEffect::Heal(amount) => {
+                    let max = actor.max_health();
+                    actor.health = cmp::min(max, actor.health + _percent_of(max, amount));
+                }
+                ItemEffect::Restore(amount) => {
+                    let max = actor.max_mana();
+                    actor.mana = cmp::min(max, actor.mana + _percent_of(max, amount));
+                }
+                ItemEffect::Cure(kind) => actor.cure(kind),
+                ItemEffect::Afflict(kind, amount) => actor.afflict(kind, amount),
+                ItemEffect::Hide(amount) => actor.invis = cmp::max(actor.invis, amount as i16),
+            }
+        }
+        !effects.is_empty()
+    })
+}
+
+pub fn describe(kind: u8) -> String {
+    if kind == ITEM_KEY {
+        return "opens locked doors".to_owned();
+    } else if kind == ITEM_LIFE_GEM {
+        return "revives a fallen teammate alongside".to_owned();
     }
-    true
+    _with_effects(kind, |effects| {
+        let descriptions: Vec<String> = effects
+            .iter()
+            .map(|effect| match *effect {
+                ItemEffect::Heal(amount) => format!("restores {}% health", amount),
+                ItemEffect::Restore(amount) => format!("restores {}% mana", amount),
+                ItemEffect::Cure(kind) => format!("cures {}", Status::new(kind, 0).name()),
+                ItemEffect::Afflict(kind, amount) => {
+                    format!("{} for {} turns", Status::new(kind, 0).name(), amount)
+                }
+                ItemEffect::Hide(amount) => format!("hides for {} turns", amount),
+            })
+            .collect();
+        descriptions.join(", ")
+    })
+}
+
+/// Items that are used up, rather than carried or worn.
+pub fn is_consumable(kind: u8) -> bool {
+    _with_effects(kind, |effects| !effects.is_empty())
+}
+
+/// Whether the AI should use this item now.
+pub fn should_use(actor: &Actor, kind: u8, is_in_danger: bool) -> bool {
+    _with_effects(kind, |effects| {
+        effects.iter().any(|effect| match *effect {
+            ItemEffect::Heal(_) => actor.is_hurt(),
+            ItemEffect::Restore(_) => actor.mana < actor.max_mana() / 2,
+            ItemEffect::Cure(kind) => actor.has_status(kind),
+            ItemEffect::Afflict(kind, _) => {
+                let status = Status::new(kind, 0);
+                !status.is_harmful() && is_in_danger && !actor.has_status(kind)
+            }
+            ItemEffect::Hide(_) => actor.is_hurt() && is_in_danger && actor.invis == 0,
+        })
+    })
+}
+
+/// Checks that the item effect config only names known effects and statuses.
+pub fn validate(config: &str) -> Result<(), Box<dyn Error>> {
+    _load_from_csv(config).map(|_effects| ())
+}
+
+fn _percent_of(max: u16, percent: u16) -> u16 {
+    (u32::from(max) * u32::from(percent) / 100) as u16
+}
+
+// Looks into the kind's effects in place, rather than copying them out of the cache.
+fn _with_effects<T, F: FnOnce(&[ItemEffect]) -> T>(kind: u8, f: F) -> T {
+    _EFFECT_CSV_CACHE.with(|effect_cache_cell| {
+        let mut effect_cache = effect_cache_cell.borrow_mut();
+        if effect_cache.is_empty() {
+            *effect_cache = _load_from_csv("config/glad/item_effect.csv").unwrap();
+        }
+        match effect_cache.get(&kind) {
+            Some(effects) => f(effects),
+            None => f(&[]),
+        }
+    })
+}
+
+fn _load_from_csv(config: &str) -> Result<HashMap<u8, Vec<ItemEffect>>, Box<dyn Error>> {
+    _read_effects(File::open(config)?, config)
+}
+
+fn _read_effects<R: io::Read>(
+    reader: R,
+    config: &str,
+) -> Result<HashMap<u8, Vec<ItemEffect>>, Box<dyn Error>> {
+    let mut effects = HashMap::new();
+    for record in csv::Reader::from_reader(reader).deserialize() {
+        let (kind, effect, status, amount): ItemEffectRow = record?;
+        let status_kind = || {
+            status::kind_named(&status)
+                .ok_or_else(|| format!("Unknown status {} (kind {}) in {}", status, kind, config))
+        };
+        let effect = match effect.as_str() {
+            "heal" => ItemEffect::Heal(amount),
+            "restore" => ItemEffect::Restore(amount),
+            "cure" => ItemEffect::Cure(status_kind()?),
+            "afflict" => ItemEffect::Afflict(status_kind()?, amount),
+            "hide" => ItemEffect::Hide(amount),
+            _ => {
+                let error = format!(
+                    "Unknown item effect {} (kind {}) in {}",
+                    effect, kind, config
+                );
+                return Err(error.into());
+            }
+        };
+        effects.entry(kind).or_insert_with(Vec::new).push(effect);
+    }
+    Ok(effects)
 }
 
 pub fn use_on_item(item: &mut Item, kind: u8) -> bool {
-    if kind == ITEM_KEY && item.kind == ITEM_DOOR {
-        item.initialize_as(ITEM_DOOR_OPEN);
-        return true;
+    match (kind, item.kind) {
+        (ITEM_KEY, ITEM_DOOR) => item.initialize_as(ITEM_DOOR_OPEN),
+        (ITEM_KEY, ITEM_CHEST_LOCKED) => item.initialize_as(ITEM_CHEST),
+        _ => return false,
     }
-    false
+    true
 }
 
 pub fn use_as_portal(
@@ -39,8 +174,56 @@ pub fn use_as_portal(
                 return portal.pos;
             }
         }
-    } else if (item.kind == ITEM_DOOR || item.kind == ITEM_TREE) && item.team != team {
+    } else if item.is_container()
+        || ((item.kind == ITEM_DOOR || item.kind == ITEM_TREE) && item.team != team)
+    {
         return from;
     }
     to
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use constants::ITEM_HEALING;
+
+    #[test]
+    fn test_effect_table() {
+        let config = "config/glad/item_effect.csv";
+        validate(config).unwrap();
+        for &kind in _load_from_csv(config).unwrap().keys() {
+            let item = Item::new(kind, 1, 0);
+            assert!(
+                item.can_keep || kind == ITEM_HEALING,
+                "{} can't be kept",
+                item.name
+            );
+            assert!(!describe(kind).is_empty());
+        }
+        // names are checked when the table loads, not when an item is used:
+        let bad_effect = "kind,effect,status,amount\n1,\"explode\",\"\",1\n";
+        assert!(_read_effects(bad_effect.as_bytes(), "test").is_err());
+        let bad_status = "kind,effect,status,amount\n1,\"cure\",\"sleepy\",0\n";
+        assert!(_read_effects(bad_status.as_bytes(), "test").is_err());
+    }
+
+    #[test]
+    fn test_use_on_actor() {
+        let mut soldier = Actor::new(0, 2, 0, (2, 2));
+        soldier.health = 1;
+        soldier.afflict(StatusKind::Poisoned, 5);
+        assert!(should_use(&soldier, 1, false));
+        assert!(use_on_actor(&mut soldier, 1));
+        assert_eq!(soldier.health, soldier.max_health());
+        assert!(!soldier.has_status(StatusKind::Poisoned));
+        assert!(!should_use(&soldier, 1, false));
+        // haste is only worth drinking when there is a fight:
+        assert!(!should_use(&soldier, 14, false));
+        assert!(should_use(&soldier, 14, true));
+        use_on_actor(&mut soldier, 14);
+        assert!(soldier.has_status(StatusKind::Hasted));
+        // nobody drinks poison on purpose:
+        assert!(!should_use(&soldier, 16, true));
+        assert!(!is_consumable(2) && !use_on_actor(&mut soldier, 2));
+    }
+}
@@ -0,0 +1,26 @@
+extern crate csv;
+extern crate inflector;
+extern crate ncurses;
+extern crate rand;
+extern crate zip;
+
+#[macro_use]
+pub mod skills;
+pub mod actor;
+pub mod constants;
+pub mod controller;
+pub mod effect;
+pub mod glad_loader;
+pub mod item;
+pub mod item_effects;
+pub mod loot;
+pub mod plan;
+pub mod progression;
+pub mod projectile;
+pub mod skills_registry;
+pub mod state;
+pub mod status;
+pub mod summon;
+pub mod view;
+pub mod vision;
+pub mod world;
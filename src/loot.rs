@@ -0,0 +1,160 @@
+// Handles what leaders carry, what creatures drop and what treasure and chests hold.
+use csv;
+use item::{Item, RARITIES};
+use skills::rand_int;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::error::Error;
+
+const LEADER_ROLLS: u16 = 3;
+const CONTAINER_ROLLS: u16 = 3;
+const RARITY_STEP: u16 = 10; // levels past which the next tier is even odds
+
+type LootEntry = (
+    String,     // source: leader, death or treasure
+    Option<u8>, // actor kind, or any
+    u16,        // lowest level
+    Option<u8>, // item kind, or nothing
+    u16,        // weight
+);
+
+thread_local!(static _LOOT_CSV_CACHE: RefCell<HashMap<String, Vec<LootEntry>>> = RefCell::new(HashMap::new()));
+
+/// What a named leader sets out carrying.
+pub fn for_leader(kind: u8, level: u16, team: usize) -> Vec<Item> {
+    (0..LEADER_ROLLS)
+        .filter_map(|_| roll("leader", Some(kind), level, team))
+        .collect()
+}
+
+/// What a creature leaves behind when it dies, if anything.
+pub fn for_death(kind: u8, level: u16, team: usize) -> Option<Item> {
+    roll("death", Some(kind), level, team)
+}
+
+/// What a treasure spot in a level turns out to hold.
+pub fn for_treasure(level: u16) -> Option<Item> {
+    roll("treasure", None, level, 0)
+}
+
+pub fn roll(source: &str, kind: Option<u8>, level: u16, team: usize) -> Option<Item> {
+    let picked = _with_entries(source, |entries| {
+        let total: u16 = entries
+            .iter()
+            .filter(|entry| _applies(entry, kind, level))
+            .map(|entry| entry.4)
+            .sum();
+        let mut pick = rand_int(total);
+        for entry in entries.iter().filter(|entry| _applies(entry, kind, level)) {
+            if pick < entry.4 {
+                return entry.3;
+            }
+            pick -= entry.4;
+        }
+        None
+    });
+    let mut item = Item::new(picked?, level, team);
+    item.set_rarity(_roll_rarity(level));
+    if item.is_container() {
+        _fill(&mut item, source, kind, level, team);
+    }
+    Some(item)
+}
+
+/// Rolls the same table again for what's inside, though never another container.
+fn _fill(container: &mut Item, source: &str, kind: Option<u8>, level: u16, team: usize) {
+    for _ii in 0..CONTAINER_ROLLS {
+        match roll(source, kind, level, team) {
+            Some(item) if !item.is_container() => container.contents.push(item),
+            _ => {}
+        }
+    }
+}
+
+/// Each tier is harder to reach than the last, and out of reach at level 0.
+fn _roll_rarity(level: u16) -> u8 {
+    let mut rarity = 0;
+    while (rarity as usize) < RARITIES.len() - 1 {
+        let step = RARITY_STEP * (u16::from(rarity) + 1);
+        if rand_int(level + step) >= level {
+            break;
+        }
+        rarity += 1;
+    }
+    rarity
+}
+
+fn _applies(entry: &LootEntry, kind: Option<u8>, level: u16) -> bool {
+    entry.2 <= level && (entry.1.is_none() || entry.1 == kind)
+}
+
+// Looks into the source's rows in place, rather than copying them out of the cache.
+fn _with_entries<T, F: FnOnce(&[LootEntry]) -> T>(source: &str, f: F) -> T {
+    _LOOT_CSV_CACHE.with(|loot_cache_cell| {
+        let mut loot_cache = loot_cache_cell.borrow_mut();
+        if loot_cache.is_empty() {
+            for entry in _load_from_csv("config/glad/loot.csv").unwrap() {
+                loot_cache
+                    .entry(entry.0.clone())
+                    .or_insert_with(Vec::new)
+                    .push(entry);
+            }
+        }
+        match loot_cache.get(source) {
+            Some(entries) => f(entries),
+            None => f(&[]),
+        }
+    })
+}
+
+fn _load_from_csv(config: &str) -> Result<Vec<LootEntry>, Box<dyn Error>> {
+    let mut entries = Vec::new();
+    for record in csv::Reader::from_path(config)?.deserialize() {
+        entries.push(record?);
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use constants::ITEM_CHEST;
+
+    #[test]
+    fn test_loot_table() {
+        for row in _load_from_csv("config/glad/loot.csv").unwrap() {
+            assert!(row.4 > 0, "{} loot can never be rolled", row.0);
+            if let Some(kind) = row.3 {
+                let item = Item::new(kind, 1, 0);
+                assert!(
+                    item.can_get || item.is_container(),
+                    "{} can't be found",
+                    kind
+                );
+            }
+        }
+        assert_eq!(_roll_rarity(0), 0);
+        let items = |source: &str, kind: Option<u8>, level: u16| -> Vec<Option<u8>> {
+            _with_entries(source, |entries| {
+                entries
+                    .iter()
+                    .filter(|entry| _applies(entry, kind, level))
+                    .map(|entry| entry.3)
+                    .collect()
+            })
+        };
+        // mages always have a shot at mana, while orcs don't:
+        assert!(items("leader", Some(3), 0).contains(&Some(15)));
+        assert!(!items("leader", Some(14), 0).contains(&Some(15)));
+        // better items turn up deeper in:
+        assert!(items("treasure", None, 0).len() < items("treasure", None, 8).len());
+        assert!(roll("nowhere", None, 0, 0).is_none());
+        let mut chest = Item::new(ITEM_CHEST, 0, 0);
+        _fill(&mut chest, "treasure", None, 0, 0);
+        assert!(chest.contents.iter().all(|item| !item.is_container()));
+        for _ii in 0..20 {
+            let loot = for_leader(3, 0, 1);
+            assert!(loot.iter().all(|item| item.team == 1 && item.rarity == 0));
+        }
+    }
+}
@@ -1,30 +1,24 @@
-extern crate csv;
-extern crate inflector;
-extern crate ncurses;
-extern crate rand;
-extern crate zip;
+extern crate gladrl;
 
-#[macro_use]
-mod skills;
-mod actor;
-mod constants;
-mod glad_loader;
-mod item;
-mod item_effects;
-mod plan;
-mod skills_registry;
-mod state;
-mod view;
-mod world;
-
-use state::State;
+use gladrl::glad_loader;
+use gladrl::item_effects;
+use gladrl::skills_registry;
+use gladrl::state::State;
+use std::env;
 
 fn main() {
+    let actor_config = format!("config/{}/actor.csv", glad_loader::CONFIG_DIRECTORY);
+    skills_registry::validate(&actor_config).expect("Actor config names unknown skills");
+    let effect_config = format!("config/{}/item_effect.csv", glad_loader::CONFIG_DIRECTORY);
+    item_effects::validate(&effect_config).expect("Item effect config names unknown effects");
     let mut game_state = State::new(
         glad_loader::CONFIG_DIRECTORY,
         glad_loader::create_random_team,
         glad_loader::load_world_and_spawn_team,
     );
+    if let Some(players) = env::args().nth(1) {
+        game_state.humans = players.parse().expect("Usage: gladrl [number of players]");
+    }
     game_state.loop_game();
     println!("Score: {}", game_state.score);
 }
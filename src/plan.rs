@@ -1,8 +1,12 @@
 // Handles tactics (for team 0), pathfinding, identifying friends/foes.
 use actor::Actor;
 use constants::ACT_MOVES;
+use csv;
 use std::collections::{HashMap, HashSet};
-use std::i32;
+use std::error::Error;
+use std::io;
+use std::path::Path;
+use vision::Vision;
 use world::World;
 
 const PLAN_ATTACK: u8 = 0;
@@ -11,6 +15,10 @@ const PLAN_DEFEND: u8 = 2;
 const PLAN_RETREAT: u8 = 3;
 const PLAN_EXIT: u8 = 4;
 
+const RELATION_ALLY: u8 = 0;
+const RELATION_NEUTRAL: u8 = 1;
+const RELATION_HOSTILE: u8 = 2;
+
 const PATH_UNKNOWN_DISTANCE: i32 = i32::MAX;
 const PATH_DONT_PROPAGATE_INTO: &str = "`'*#^";
 const PATH_DONT_PROPAGATE_OUT_OF: &str = "~`'*#%^";
@@ -20,8 +28,12 @@ pub struct Plan {
     _tactics: HashMap<usize, u8>,
     _muster_point: HashMap<usize, (u16, u16)>,
     _occupied_cells: HashMap<(u16, u16), usize>,
+    _wounded_cells: HashSet<(u16, u16)>,
     _world_size: (u16, u16),
     _distances: HashMap<usize, Vec<i32>>,
+    _last_seen: HashMap<usize, Vec<(u16, u16)>>,
+    _visions: HashMap<usize, Vision>,
+    _relations: HashMap<(usize, usize), u8>,
 }
 
 impl Plan {
@@ -30,9 +42,13 @@ impl Plan {
             _world_size,
             _distances: HashMap::new(),
             _occupied_cells: HashMap::new(),
+            _wounded_cells: HashSet::new(),
             _enemies: HashMap::new(),
             _tactics: HashMap::new(),
             _muster_point: HashMap::new(),
+            _last_seen: HashMap::new(),
+            _visions: HashMap::new(),
+            _relations: HashMap::new(),
         };
         for &team in teams {
             let area = (_world_size.1 * _world_size.0) as usize;
@@ -40,6 +56,8 @@ impl Plan {
                 .insert(team, vec![PATH_UNKNOWN_DISTANCE; area]);
             plan._enemies.insert(team, 0);
             plan._muster_point.insert(team, (0, 0));
+            plan._last_seen.insert(team, Vec::new());
+            plan._visions.insert(team, Vision::new(_world_size));
             plan._tactics
                 .insert(team, if team == 0 { PLAN_FOLLOW } else { PLAN_ATTACK });
         }
@@ -71,6 +89,10 @@ impl Plan {
         self._tactics[&team] == PLAN_DEFEND
     }
 
+    pub fn is_following(&self, team: usize) -> bool {
+        self._tactics[&team] == PLAN_FOLLOW
+    }
+
     pub fn is_attacking(&self, team: usize) -> bool {
         self._tactics[&team] == PLAN_ATTACK
     }
@@ -79,12 +101,79 @@ impl Plan {
         self._tactics[&team] == PLAN_RETREAT
     }
 
+    /// Rows of (scenario, team, other team, relation); teams default to hostile,
+    /// also when the config has no relations file at all.
+    pub fn load_relations(&mut self, config: &str, scenario: usize) -> Result<(), Box<dyn Error>> {
+        let path = format!("config/{}/relations.csv", config);
+        if !Path::new(&path).exists() {
+            return Ok(());
+        }
+        self._read_relations(csv::Reader::from_path(&path)?, scenario, &path)
+    }
+
+    fn _read_relations<R: io::Read>(
+        &mut self,
+        mut reader: csv::Reader<R>,
+        scenario: usize,
+        path: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        for record in reader.deserialize() {
+            let (row_scenario, team, other, relation): (usize, usize, usize, String) = record?;
+            if row_scenario != scenario {
+                continue;
+            }
+            match relation.as_str() {
+                "ally" => self._set_relation(team, other, RELATION_ALLY),
+                "neutral" => self._set_relation(team, other, RELATION_NEUTRAL),
+                "hostile" => self._set_relation(team, other, RELATION_HOSTILE),
+                _ => panic!("Unknown relation '{}' in {}", relation, path),
+            }
+        }
+        Ok(())
+    }
+
+    /// Neutrals turn hostile once attacked; returns true if that happened.
+    pub fn provoke(&mut self, aggressor: usize, victim: usize) -> bool {
+        if self._relation(aggressor, victim) != RELATION_NEUTRAL {
+            return false;
+        }
+        self._set_relation(aggressor, victim, RELATION_HOSTILE);
+        true
+    }
+
+    fn _set_relation(&mut self, team: usize, other: usize, relation: u8) {
+        self._relations.insert((team, other), relation);
+        self._relations.insert((other, team), relation);
+    }
+
+    fn _relation(&self, team: usize, other: usize) -> u8 {
+        if team == other {
+            return RELATION_ALLY;
+        }
+        match self._relations.get(&(team, other)) {
+            Some(&relation) => relation,
+            None => RELATION_HOSTILE,
+        }
+    }
+
+    pub fn is_hostile(&self, team: usize, other: usize) -> bool {
+        self._relation(team, other) == RELATION_HOSTILE
+    }
+
+    pub fn is_allied(&self, team: usize, other: usize) -> bool {
+        self._relation(team, other) == RELATION_ALLY
+    }
+
     pub fn fast_update(&mut self, actors: &[Actor]) {
         self._occupied_cells.clear();
+        self._wounded_cells.clear();
         self._enemies.insert(0, 0);
         for actor in actors.iter().filter(|actor| actor.is_combatant()) {
             self._occupied_cells.insert(actor.pos, actor.team);
-            if actor.team != 0 {
+            if actor.is_hurt() {
+                self._wounded_cells.insert(actor.pos);
+            }
+            if self.is_hostile(actor.team, 0) {
                 let current_enemies = self._enemies[&0];
                 self._enemies.insert(0, current_enemies + 1);
             }
@@ -95,11 +184,21 @@ impl Plan {
         if self._enemies[&0] == 0 && self._tactics[&0] == PLAN_ATTACK {
             self._tactics.insert(0, PLAN_EXIT);
         }
+        let opaque = world.opaque_cells();
         for &team in teams {
+            if let Some(vision) = self._visions.get_mut(&team) {
+                vision.update(team, world, actors, &opaque);
+            }
+            self._spot_enemies(team, world, actors);
             self._update_paths(team, world, actors);
         }
     }
 
+    /// What a team can see and remembers.
+    pub fn vision(&self, team: usize) -> &Vision {
+        &self._visions[&team]
+    }
+
     fn _update_paths(&mut self, team: usize, world: &World, actors: &[Actor]) {
         let maximum_steps = if team == 0 { 200 } else { 12 };
         let mut open_list = self._open_list(team, world, actors);
@@ -116,7 +215,7 @@ impl Plan {
             PLAN_EXIT => world.exits(),
             PLAN_DEFEND => vec![self._muster_point(team)],
             PLAN_FOLLOW => self._locate_leaders(team, actors),
-            PLAN_ATTACK | PLAN_RETREAT => self._locate_enemies(team, actors),
+            PLAN_ATTACK | PLAN_RETREAT => self._locate_enemies(team),
             _ => Vec::new(),
         }
     }
@@ -154,12 +253,38 @@ impl Plan {
         next_open_list
     }
 
-    fn _locate_enemies(&self, team: usize, actors: &[Actor]) -> Vec<(u16, u16)> {
-        actors
+    // Remember where enemies were last seen; forget the spots we've since reached or seen empty.
+    fn _spot_enemies(&mut self, team: usize, world: &World, actors: &[Actor]) {
+        let lookouts: Vec<&Actor> = actors
             .iter()
-            .filter(|actor| actor.is_enemy_of(team) && actor.invis == 0)
-            .map(|actor| actor.pos)
-            .collect()
+            .filter(|actor| actor.team == team && actor.is_combatant())
+            .collect();
+        let spotted: Vec<(u16, u16)> = actors
+            .iter()
+            .filter(|actor| actor.is_enemy_of(team, self))
+            .filter(|enemy| {
+                let vision = &self._visions[&team];
+                vision.is_visible(enemy.pos)
+                    && (enemy.invis == 0
+                        || lookouts.iter().any(|lookout| lookout.can_see(enemy, world)))
+            })
+            .map(|enemy| enemy.pos)
+            .collect();
+        let vision = &self._visions[&team];
+        if let Some(last_seen) = self._last_seen.get_mut(&team) {
+            last_seen.retain(|&pos| {
+                !vision.is_visible(pos) && !lookouts.iter().any(|lookout| lookout.pos == pos)
+            });
+            for pos in spotted {
+                if !last_seen.contains(&pos) {
+                    last_seen.push(pos);
+                }
+            }
+        }
+    }
+
+    fn _locate_enemies(&self, team: usize) -> Vec<(u16, u16)> {
+        self._last_seen[&team].clone()
     }
 
     fn _locate_leaders(&self, team: usize, actors: &[Actor]) -> Vec<(u16, u16)> {
@@ -183,6 +308,14 @@ impl Plan {
         }
     }
 
+    /// Teams that don't know where any enemies are can be caught by surprise.
+    pub fn is_aware(&self, team: usize) -> bool {
+        match self._last_seen.get(&team) {
+            Some(last_seen) => !last_seen.is_empty(),
+            None => true,
+        }
+    }
+
     pub fn is_near_enemy(&self, pos: (u16, u16), team: usize) -> bool {
         (self.is_attacking(team) || self.is_retreating(team))
             && self.distance_to_goal(pos, team) < 10
@@ -195,6 +328,10 @@ impl Plan {
     pub fn whos_at(&self, pos: (u16, u16)) -> Option<&usize> {
         self._occupied_cells.get(&pos)
     }
+
+    pub fn is_wounded_at(&self, pos: (u16, u16)) -> bool {
+        self._wounded_cells.contains(&pos)
+    }
 }
 
 #[cfg(test)]
@@ -208,7 +345,13 @@ mod tests {
         world.reshape((5, 5));
         let plan = Plan::new((5, 5), &team_idxs);
         let actors = vec![Actor::new(1, 1, 0, (0, 0)), Actor::new(1, 1, 1, (1, 4))];
-        return (plan, world, actors, team_idxs);
+        (plan, world, actors, team_idxs)
+    }
+
+    fn load_relations_fixture(plan: &mut Plan, scenario: usize) {
+        let rows = "scenario,team,other team,relation\n2,1,2,ally\n25,0,1,neutral\n";
+        let reader = csv::Reader::from_reader(rows.as_bytes());
+        plan._read_relations(reader, scenario, "fixture").unwrap();
     }
 
     #[test]
@@ -226,6 +369,26 @@ mod tests {
         assert!(!(plan.is_attacking(0) || plan.is_defending(0) || plan.is_retreating(0)));
     }
 
+    #[test]
+    fn test_relations() {
+        let (mut plan, _, actors, _) = fixtures();
+        assert!(plan.is_allied(1, 1) && plan.is_hostile(0, 1));
+        load_relations_fixture(&mut plan, 25);
+        assert!(!plan.is_hostile(0, 1) && !plan.is_allied(0, 1));
+        assert!(plan.is_hostile(1, 2));
+        plan.fast_update(&actors);
+        assert_eq!(plan.num_enemies(), 0);
+        assert!(plan.provoke(0, 1));
+        assert!(plan.is_hostile(1, 0) && !plan.provoke(0, 1));
+        load_relations_fixture(&mut plan, 2);
+        assert!(plan.is_allied(2, 1));
+
+        // a config without a relations file leaves everyone hostile:
+        let (mut plan, _, _, _) = fixtures();
+        plan.load_relations("missing", 25).unwrap();
+        assert!(plan.is_hostile(0, 1));
+    }
+
     #[test]
     fn test_fast_update_and_whos_at() {
         let (mut plan, _, actors, _) = fixtures();
@@ -249,4 +412,35 @@ mod tests {
                 .all(|distance| distance != &PATH_UNKNOWN_DISTANCE));
         }
     }
+
+    #[test]
+    fn test_sight_and_memory() {
+        let (mut plan, mut world, mut actors, team_idxs) = fixtures();
+        plan.tactic_attack();
+        assert!(!plan.is_aware(0));
+        plan.update(&team_idxs, &world, &actors);
+        assert!(plan.is_aware(0) && plan.is_aware(1));
+        // a wall goes up between the teams, but the last sighting is remembered:
+        for xx in 0..5 {
+            world.tiles[10 + xx] = 0;
+        }
+        actors[1].pos = (2, 4);
+        plan.update(&team_idxs, &world, &actors);
+        assert_eq!(plan._locate_enemies(0), vec![(1, 4)]);
+        // arriving at the last known position clears it:
+        actors[0].pos = (1, 4);
+        actors[1].pos = (4, 4);
+        actors[1].invis = 10;
+        plan.update(&team_idxs, &world, &actors);
+        assert!(plan._locate_enemies(0).is_empty());
+        assert!(!plan.is_aware(0));
+        // new sightings add to what is remembered rather than replacing it:
+        actors[1].invis = 0;
+        plan.update(&team_idxs, &world, &actors);
+        actors[0].pos = (0, 0);
+        actors[1].pos = (2, 4);
+        actors.push(Actor::new(1, 1, 1, (3, 0)));
+        plan.update(&team_idxs, &world, &actors);
+        assert_eq!(plan._locate_enemies(0), vec![(4, 4), (3, 0)]);
+    }
 }
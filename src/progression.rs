@@ -0,0 +1,51 @@
+// Handles which skills actors unlock as they level up.
+use csv;
+use std::cell::RefCell;
+use std::error::Error;
+
+type Unlock = (
+    u8,     // kind
+    u16,    // level
+    String, // skill
+    String, // skill it replaces (if any)
+);
+
+thread_local!(static _PROGRESSION_CSV_CACHE: RefCell<Vec<Unlock>> = const { RefCell::new(Vec::new()) });
+
+/// Skills unlocked at exactly this level; more than one means a choice.
+pub fn unlocks(kind: u8, level: u16) -> Vec<(String, String)> {
+    _load_from_csv("config/glad/progression.csv")
+        .unwrap()
+        .into_iter()
+        .filter(|unlock| unlock.0 == kind && unlock.1 == level)
+        .map(|unlock| (unlock.2, unlock.3))
+        .collect()
+}
+
+fn _load_from_csv(config: &str) -> Result<Vec<Unlock>, Box<dyn Error>> {
+    _PROGRESSION_CSV_CACHE.with(|progression_cache_cell| {
+        let mut progression_cache = progression_cache_cell.borrow_mut();
+        if progression_cache.is_empty() {
+            for record in csv::Reader::from_path(config)?.deserialize() {
+                progression_cache.push(record?);
+            }
+        }
+        Ok(progression_cache.clone())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use skills_registry;
+
+    #[test]
+    fn test_unlocks() {
+        let names = skills_registry::names();
+        for unlock in _load_from_csv("config/glad/progression.csv").unwrap() {
+            assert!(names.contains(&unlock.2), "{} is not a skill", unlock.2);
+        }
+        assert_eq!(unlocks(0, 3).len(), 2);
+        assert!(unlocks(0, 1).is_empty());
+    }
+}
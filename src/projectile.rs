@@ -0,0 +1,108 @@
+// Handles how thrown and fired actors fly: their range, speed, and what
+// they do when they hit something.
+use csv;
+use std::cell::RefCell;
+use std::error::Error;
+
+type ProjectileStats = (
+    u8,  // kind
+    u16, // range
+    u16, // speed
+    u16, // pierce
+    u16, // ricochet
+    u8,  // returns
+);
+
+thread_local!(static _PROJECTILE_CSV_CACHE: RefCell<Vec<ProjectileStats>> = const { RefCell::new(Vec::new()) });
+
+pub struct Flight {
+    pub speed: u16,    // cells moved per turn
+    pub range: u16,    // cells moved before dropping (or turning back)
+    pub pierce: u16,   // combatants it can still pass through
+    pub ricochet: u16, // walls it can still bounce off
+    pub returns: bool,
+    pub is_returning: bool,
+    pub traveled: u16,
+}
+
+impl Flight {
+    /// How actors of this kind fly, if they are projectiles at all.
+    pub fn new(kind: u8) -> Option<Flight> {
+        let row = _load_from_csv("config/glad/projectile.csv")
+            .unwrap()
+            .into_iter()
+            .find(|row| row.0 == kind)?;
+        Some(Flight {
+            range: row.1,
+            speed: row.2,
+            pierce: row.3,
+            ricochet: row.4,
+            returns: row.5 != 0,
+            is_returning: false,
+            traveled: 0,
+        })
+    }
+
+    /// Counts a step, returning false once the projectile is out of range.
+    pub fn advance(&mut self) -> bool {
+        self.traveled += 1;
+        if self.traveled < self.range {
+            return true;
+        }
+        self.turn_back()
+    }
+
+    /// Starts heading home, returning false if this projectile can't.
+    pub fn turn_back(&mut self) -> bool {
+        if self.returns && !self.is_returning {
+            self.is_returning = true;
+            self.traveled = 0;
+            return true;
+        }
+        false
+    }
+}
+
+/// The direction to take after bouncing off a wall (diagonals glance off).
+pub fn reflect(direction: u8, is_open: &dyn Fn(u8) -> bool) -> u8 {
+    if direction % 2 == 1 {
+        for &turn in &[2, 6] {
+            let bounce = (direction + turn) % 8;
+            if is_open(bounce) {
+                return bounce;
+            }
+        }
+    }
+    (direction + 4) % 8
+}
+
+fn _load_from_csv(config: &str) -> Result<Vec<ProjectileStats>, Box<dyn Error>> {
+    _PROJECTILE_CSV_CACHE.with(|projectile_cache_cell| {
+        let mut projectile_cache = projectile_cache_cell.borrow_mut();
+        if projectile_cache.is_empty() {
+            for record in csv::Reader::from_path(config)?.deserialize() {
+                projectile_cache.push(record?);
+            }
+        }
+        Ok(projectile_cache.clone())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flight() {
+        assert!(Flight::new(0).is_none());
+        let mut boomerang = Flight::new(53).unwrap();
+        while boomerang.advance() && !boomerang.is_returning {}
+        assert!(boomerang.is_returning);
+        while boomerang.advance() {}
+        assert_eq!(boomerang.traveled, boomerang.range);
+        // diagonals glance off walls, straight shots bounce back:
+        assert_eq!(reflect(1, &|dir| dir == 7), 7);
+        assert_eq!(reflect(1, &|_dir| false), 5);
+        assert_eq!(reflect(2, &|_dir| true), 6);
+    }
+}
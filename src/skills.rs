@@ -1,14 +1,43 @@
 // Handles actors' special abilities and side effects.
 use actor::Actor;
-use constants::{ITEM_DOOR, ITEM_DOOR_OPEN, ITEM_TELEPORT_MARKER, TILE_BLOOD, TILE_TREE};
+use constants::{
+    EFFECT_CLOUD, EFFECT_CONE, EFFECT_EXPLOSION, TARGET_ALLY, TARGET_CELL, TARGET_DIRECTION,
+    TARGET_NONE, TILE_TREE,
+};
+use constants::{ITEM_CHEST, ITEM_CHEST_LOCKED};
+use constants::{ITEM_DOOR, ITEM_DOOR_OPEN, ITEM_TELEPORT_MARKER, TILE_BLOOD};
+use csv;
+use effect::Effect;
 use inflector::Inflector;
 use item::Item;
-
 use plan::Plan;
 use rand::*;
-use std::{cmp, u16};
+use status::StatusKind;
+use std::cell::RefCell;
+use std::cmp;
+use std::collections::HashMap;
+use std::error::Error;
+use summon;
 use world::World; // for to_sentence_case
 
+type SkillStats = (
+    String, // name
+    u16,    // mana cost
+    u16,    // reach: how far the skill hits, or can be aimed (0 means anywhere in sight)
+    u16,    // extra reach per level of the actor
+    u16,    // distance to the goal that decides when the AI uses the skill
+    u8,     // kind of actor or item spawned
+    u16,    // magnitude
+    u16,    // radius of the area affected
+    i32,    // weight when the AI scores the skill
+    u16,    // cooldown in turns
+    u16,    // wind-up in turns
+    String, // what the player aims the skill at
+    String, // description
+);
+
+thread_local!(static _SKILL_CSV_CACHE: RefCell<HashMap<String, SkillStats>> = RefCell::new(HashMap::new()));
+
 // hook simple skills into any part of the actor's behavior
 macro_rules! passive_effect {
     ($skill:ident => $actor:expr) => {
@@ -23,33 +52,86 @@ macro_rules! passive_effect {
     };
 }
 
-/// Set actor's current skill to this skill and return from current method
-macro_rules! choose_skill {
-    ($should:ident if $can:ident => $actor:expr, $( $arg:expr ),*) => {
-        // split at 4 turns 'can_teleport' into 'teleport' for example
-        let skill_name = &stringify!($can)[4..];
-        if $actor.has_skill(skill_name) &&
-            $can($actor, $($arg,)*) && $should($actor, $($arg,)*) {
-                $actor.select_skill(skill_name);
-                return true;
-            }
-    };
+pub fn rand_int(max: u16) -> u16 {
+    thread_rng().gen_range(0, cmp::max(1, max))
 }
 
-/// Use actor's current skill and return from the current method
-macro_rules! use_skill {
-    ($skill:ident if $can:ident => $actor:expr, $world:expr, $p:expr, $spawn:expr) => {
-        if $actor.selected_skill() == stringify!($skill) {
-            if $can($actor, $world, $p) {
-                return $skill($actor, $world, $p, $spawn);
+pub fn cost(skill: &str) -> u16 {
+    _with_stats(skill, |stats| stats.1).unwrap()
+}
+
+pub fn reach(skill: &str) -> u16 {
+    _with_stats(skill, |stats| stats.2).unwrap()
+}
+
+/// The skill's reach for this actor, growing with its level.
+pub fn reach_of(slf: &Actor, skill: &str) -> u16 {
+    _with_stats(skill, |stats| stats.2 + slf.level * stats.3).unwrap()
+}
+
+pub fn engage(skill: &str) -> i32 {
+    _with_stats(skill, |stats| i32::from(stats.4)).unwrap()
+}
+
+pub fn spawns(skill: &str) -> u8 {
+    _with_stats(skill, |stats| stats.5).unwrap()
+}
+
+pub fn magnitude(skill: &str) -> u16 {
+    _with_stats(skill, |stats| stats.6).unwrap()
+}
+
+pub fn radius(skill: &str) -> u16 {
+    _with_stats(skill, |stats| stats.7).unwrap()
+}
+
+pub fn weight(skill: &str) -> i32 {
+    _with_stats(skill, |stats| stats.8).unwrap()
+}
+
+pub fn cooldown(skill: &str) -> u16 {
+    _with_stats(skill, |stats| stats.9).unwrap()
+}
+
+pub fn windup(skill: &str) -> u16 {
+    _with_stats(skill, |stats| stats.10).unwrap()
+}
+
+pub fn targeting(skill: &str) -> u8 {
+    _with_stats(skill, |stats| match stats.11.as_str() {
+        "direction" => TARGET_DIRECTION,
+        "cell" => TARGET_CELL,
+        "ally" => TARGET_ALLY,
+        _ => TARGET_NONE,
+    })
+    .unwrap()
+}
+
+pub fn description(skill: &str) -> String {
+    _with_stats(skill, |stats| stats.12.clone()).unwrap()
+}
+
+// Looks into the skill's row in place, rather than copying it out of the cache.
+fn _with_stats<T, F: FnOnce(&SkillStats) -> T>(skill: &str, f: F) -> Result<T, Box<dyn Error>> {
+    let config = "config/glad/skill.csv";
+    _SKILL_CSV_CACHE.with(|skill_cache_cell| {
+        let mut skill_cache = skill_cache_cell.borrow_mut();
+        if skill_cache.is_empty() {
+            for record in csv::Reader::from_path(config)?.deserialize() {
+                let row: SkillStats = record?;
+                skill_cache.insert(row.0.clone(), row);
             }
-            $actor.log_action("was too tired!");
         }
-    };
+        match skill_cache.get(skill) {
+            Some(stats) => Ok(f(stats)),
+            None => Err(format!("Unable to load {} from {}", skill, config).into()),
+        }
+    })
 }
 
-pub fn rand_int(max: u16) -> u16 {
-    thread_rng().gen_range(0, cmp::max(1, max))
+// Skills that cost mana become less attractive as mana runs out.
+fn _weigh_by_mana(slf: &Actor, skill: &str) -> i32 {
+    weight(skill) * i32::from(slf.mana) / i32::from(slf.max_mana())
 }
 
 fn _raycast(slf: &Actor, dir: u8, wld: &World, p: &Plan, len: u16) -> Option<(usize, u16)> {
@@ -67,20 +149,71 @@ fn _raycast(slf: &Actor, dir: u8, wld: &World, p: &Plan, len: u16) -> Option<(us
     None
 }
 
+// Where something thrown in the actor's direction would land.
+fn _impact(slf: &Actor, wld: &World, p: &Plan, len: u16) -> (u16, u16) {
+    let mut pos = slf.pos;
+    for _dist in 0..len {
+        let new_pos = wld.neighbor(pos, slf.direction, slf.team, "#%\"'");
+        if new_pos == pos || p.whos_at(new_pos).is_some() {
+            return new_pos;
+        }
+        pos = new_pos;
+    }
+    pos
+}
+
+// Area effects are worth using if they catch enemies and spare allies.
+fn _score_area(slf: &Actor, effect: &Effect, wld: &World, p: &Plan, skill: &str) -> i32 {
+    let (mut enemies, mut allies) = (0, 0);
+    for pos in effect.cells(wld) {
+        match p.whos_at(pos) {
+            Some(&team) if p.is_hostile(slf.team, team) => enemies += 1,
+            Some(&team) if p.is_allied(slf.team, team) => allies += 1,
+            _ => {}
+        }
+    }
+    if enemies == 0 || allies > 0 {
+        return 0;
+    }
+    _weigh_by_mana(slf, skill) * cmp::min(enemies, 3)
+}
+
+// A projectile flying away from its owner, who gets credit for its hits.
+fn _launch(slf: &Actor, kind: u8, level: u16, direction: u8) -> Actor {
+    let mut projectile = Actor::new(kind, level, slf.team, slf.pos);
+    projectile.direction = direction;
+    projectile.owner = Some(slf.uid);
+    projectile
+}
+
+// A new actor bound to whoever called it, for as long as the skill allows.
+fn _summon(slf: &mut Actor, skill: &str, kind: u8, level: u16, pos: (u16, u16)) -> Actor {
+    let mut summoned = Actor::new(kind, level, slf.team, pos);
+    summoned.summoner = Some(slf.uid);
+    summoned.is_bound = summon::is_bound(skill);
+    summoned.lifetime = summon::lifetime(skill);
+    slf.summons += 1;
+    summoned
+}
+
+fn _can_summon(slf: &Actor, skill: &str) -> bool {
+    slf.summons < summon::limit(skill)
+}
+
 pub fn passive_spin(slf: &mut Actor) {
-    if slf.time % 2 == 0 {
+    if slf.time.is_multiple_of(2) {
         slf.direction = (slf.direction + 1) % 8;
     }
 }
 
 pub fn passive_drift(slf: &mut Actor, wld: &World) {
-    if slf.time % 10 == 0 {
+    if slf.time.is_multiple_of(10) {
         let drift_dir = match slf.random_seed % 4 {
             0 => slf.direction + 2,
             1 => slf.direction + 6,
             _ => slf.direction,
         };
-        let pos = wld.neighbor(slf.pos, drift_dir % 8, slf.team, &slf.walls);
+        let pos = wld.neighbor(slf.pos, drift_dir % 8, slf.team, &slf.walls());
         if pos == slf.pos {
             return slf.lose_momentum(1);
         }
@@ -89,7 +222,7 @@ pub fn passive_drift(slf: &mut Actor, wld: &World) {
 }
 
 pub fn passive_descend(slf: &mut Actor, wld: &mut World) {
-    slf.hurt(1, wld);
+    slf.hurt(magnitude("passive_descend"), wld);
     if slf.momentum == 0 {
         slf.act_die(wld);
     }
@@ -98,10 +231,11 @@ pub fn passive_descend(slf: &mut Actor, wld: &mut World) {
 pub fn passive_slam(slf: &mut Actor, action: u8, vic: &mut Actor, wld: &mut World, p: &Plan) {
     if slf.momentum != 0 && vic.is_mobile() && slf.direction == action {
         slf.log_interaction("slammed into", vic);
-        vic.stun(2);
-        for _ii in 0..2 {
+        let force = magnitude("passive_slam");
+        vic.stun(force as i16);
+        for _ii in 0..force {
             wld.change_tiles(vic.pos, TILE_BLOOD);
-            let pos = wld.neighbor(vic.pos, slf.direction, vic.team, &vic.walls);
+            let pos = wld.neighbor(vic.pos, slf.direction, vic.team, &vic.walls());
             match p.whos_at(pos) {
                 None => vic.pos = pos,
                 Some(&_team) => return,
@@ -115,7 +249,7 @@ pub fn passive_whirl(slf: &mut Actor, action: u8, vic: &mut Actor) {
     let right = (slf.direction + 6) % 8;
     if action == left || action == right {
         slf.log_interaction("whirled at", vic);
-        vic.stun(1);
+        vic.stun(magnitude("passive_whirl") as i16);
     }
 }
 
@@ -123,7 +257,7 @@ pub fn passive_trip(slf: &mut Actor, dir: u8, vic: &mut Actor) {
     let angle = (i16::from(dir) - i16::from(slf.direction)).abs();
     if angle == 3 || angle == 4 || angle == 5 {
         slf.log_interaction("spun and tripped", vic);
-        vic.stun(1);
+        vic.stun(magnitude("passive_trip") as i16);
     }
 }
 
@@ -142,12 +276,28 @@ pub fn passive_backstab(slf: &mut Actor, dir: u8, vic: &mut Actor) {
     }
 }
 
-pub fn passive_heal(slf: &mut Actor, pal: &mut Actor, _ww: &mut World) {
-    if slf.mana >= 5 && pal.health < pal.max_health() {
-        slf.act_exert(5, &format!("healed {}.", pal.name));
+pub fn passive_burn(slf: &mut Actor, vic: &mut Actor) {
+    if vic.is_flesh() {
+        slf.log_interaction("scorched", vic);
+        vic.afflict(StatusKind::Burning, magnitude("passive_burn"));
+    }
+}
+
+pub fn passive_counter(slf: &mut Actor, attacker: &mut Actor, wld: &mut World) {
+    if slf.is_combatant() && slf.stun == 0 && attacker.is_combatant() {
+        slf.log_interaction("countered", attacker);
+        let damage = slf.strength() * slf.level * magnitude("passive_counter") / 100;
+        attacker.hurt(cmp::max(1, damage), wld);
+    }
+}
+
+pub fn passive_heal(slf: &mut Actor, pal: &mut Actor, _ww: &mut World, p: &Plan) {
+    let cost = cost("passive_heal");
+    if slf.mana >= cost && pal.health < pal.max_health() && p.is_allied(slf.team, pal.team) {
+        slf.act_exert(cost, &format!("healed {}.", pal.name));
         let time = slf.time;
         pal.log_event(&format!("{} healed me.", slf.name.to_sentence_case()), time);
-        pal.recover(20);
+        pal.recover(magnitude("passive_heal"));
     }
 }
 
@@ -159,8 +309,8 @@ pub fn passive_aim(slf: &mut Actor, wld: &World, p: &Plan) {
     let mut closest = u16::MAX;
     let init_dir = slf.direction;
     for dir in (0..8).map(|delta_dir| (init_dir + delta_dir) % 8) {
-        if let Some((team, dist)) = _raycast(slf, dir, wld, p, 10) {
-            if dist < closest && team != slf.team {
+        if let Some((team, dist)) = _raycast(slf, dir, wld, p, reach("passive_aim")) {
+            if dist < closest && p.is_hostile(slf.team, team) {
                 closest = dist;
                 slf.direction = dir;
             }
@@ -169,15 +319,18 @@ pub fn passive_aim(slf: &mut Actor, wld: &World, p: &Plan) {
 }
 
 pub fn can_sprint(slf: &Actor, _wld: &World, _p: &Plan) -> bool {
-    slf.momentum > 0 && slf.mana >= 2
+    slf.momentum > 0 && slf.mana >= cost("sprint")
 }
-pub fn should_sprint(slf: &Actor, _wld: &World, _p: &Plan) -> bool {
-    !slf.is_hurt() && rand_int(10) == 0
+pub fn score_sprint(slf: &Actor, _wld: &World, p: &Plan) -> i32 {
+    if slf.is_hurt() || p.distance_to_goal(slf.pos, slf.team) < engage("sprint") {
+        return 0;
+    }
+    _weigh_by_mana(slf, "sprint")
 }
 pub fn sprint(slf: &mut Actor, wld: &mut World, p: &Plan, _spawn: &mut Vec<Actor>) {
-    slf.act_exert(2, "sprinted ahead.");
-    for _ii in 0..3 {
-        let new_pos = wld.neighbor(slf.pos, slf.direction, slf.team, &slf.walls);
+    slf.act_exert(cost("sprint"), "sprinted ahead.");
+    for _ii in 0..magnitude("sprint") {
+        let new_pos = wld.neighbor(slf.pos, slf.direction, slf.team, &slf.walls());
         match p.whos_at(new_pos) {
             None => slf.pos = new_pos,
             _ => break,
@@ -187,15 +340,20 @@ pub fn sprint(slf: &mut Actor, wld: &mut World, p: &Plan, _spawn: &mut Vec<Actor
 }
 
 pub fn can_charge(slf: &Actor, _wld: &World, _p: &Plan) -> bool {
-    slf.mana >= 4
+    slf.mana >= cost("charge")
 }
-pub fn should_charge(_slf: &Actor, _wld: &World, _p: &Plan) -> bool {
-    rand_int(60) == 0
+pub fn score_charge(slf: &Actor, wld: &World, p: &Plan) -> i32 {
+    match _raycast(slf, slf.direction, wld, p, reach("charge")) {
+        Some((team, dist)) if dist > 0 && p.is_hostile(slf.team, team) => {
+            _weigh_by_mana(slf, "charge")
+        }
+        _ => 0,
+    }
 }
 pub fn charge(slf: &mut Actor, wld: &mut World, p: &Plan, _spawn: &mut Vec<Actor>) {
-    slf.act_exert(4, "charged!");
-    for _step in 0..2 {
-        let new_pos = wld.neighbor(slf.pos, slf.direction, slf.team, &slf.walls);
+    slf.act_exert(cost("charge"), "charged!");
+    for _step in 0..magnitude("charge") {
+        let new_pos = wld.neighbor(slf.pos, slf.direction, slf.team, &slf.walls());
         if let Some(&team) = p.whos_at(new_pos) {
             if team == slf.team {
                 return slf.lose_momentum(1);
@@ -206,17 +364,38 @@ pub fn charge(slf: &mut Actor, wld: &mut World, p: &Plan, _spawn: &mut Vec<Actor
     }
 }
 
+pub fn can_cleave(slf: &Actor, _wld: &World, _p: &Plan) -> bool {
+    slf.mana >= cost("cleave")
+}
+fn _cleave(slf: &Actor) -> Effect {
+    let damage = magnitude("cleave") * slf.strength() * slf.level;
+    let mut cleave = Effect::new(EFFECT_CONE, slf.pos, radius("cleave"), damage);
+    cleave.direction = slf.direction;
+    cleave.owner = Some(slf.uid);
+    cleave
+}
+pub fn score_cleave(slf: &Actor, wld: &World, p: &Plan) -> i32 {
+    _score_area(slf, &_cleave(slf), wld, p, "cleave")
+}
+pub fn cleave(slf: &mut Actor, wld: &mut World, _p: &Plan, _spawn: &mut Vec<Actor>) {
+    slf.act_exert(cost("cleave"), "swung in a wide arc!");
+    wld.add_effect(_cleave(slf));
+}
+
 pub fn can_leap(slf: &Actor, _wld: &World, _p: &Plan) -> bool {
-    slf.mana >= 1
+    slf.mana >= cost("leap")
 }
-pub fn should_leap(slf: &Actor, _wld: &World, _p: &Plan) -> bool {
-    slf.is_hurt()
+pub fn score_leap(slf: &Actor, _wld: &World, p: &Plan) -> i32 {
+    if slf.is_hurt() && p.is_near_enemy(slf.pos, slf.team) {
+        return weight("leap");
+    }
+    0
 }
 pub fn leap(slf: &mut Actor, wld: &mut World, p: &Plan, _spawn: &mut Vec<Actor>) {
-    slf.act_exert(1, "leapt back!");
+    slf.act_exert(cost("leap"), "leapt back!");
     slf.direction = (slf.direction + 4) % 8;
-    for _step in 0..2 {
-        let new_pos = wld.neighbor(slf.pos, slf.direction, slf.team, &slf.walls);
+    for _step in 0..magnitude("leap") {
+        let new_pos = wld.neighbor(slf.pos, slf.direction, slf.team, &slf.walls());
         if let Some(&team) = p.whos_at(new_pos) {
             if team == slf.team {
                 return slf.lose_momentum(1);
@@ -228,112 +407,179 @@ pub fn leap(slf: &mut Actor, wld: &mut World, p: &Plan, _spawn: &mut Vec<Actor>)
 }
 
 pub fn can_cloak(slf: &Actor, _wld: &World, _p: &Plan) -> bool {
-    slf.mana >= 5
+    slf.mana >= cost("cloak")
 }
-pub fn should_cloak(slf: &Actor, _wld: &World, p: &Plan) -> bool {
-    slf.is_hurt() && !p.is_near_enemy(slf.pos, slf.team)
+pub fn score_cloak(slf: &Actor, _wld: &World, p: &Plan) -> i32 {
+    if slf.is_hurt() && slf.invis == 0 && !p.is_near_enemy(slf.pos, slf.team) {
+        return weight("cloak");
+    }
+    0
 }
 pub fn cloak(slf: &mut Actor, _wld: &mut World, _p: &Plan, _spawn: &mut Vec<Actor>) {
-    slf.act_exert(5, "started to sneak around.");
-    slf.invis += 10;
+    slf.act_exert(cost("cloak"), "started to sneak around.");
+    slf.invis += magnitude("cloak") as i16;
 }
 
 pub fn can_shoot(slf: &Actor, _wld: &World, _p: &Plan) -> bool {
-    slf.mana >= 2
+    slf.mana >= cost("shoot")
 }
-pub fn should_shoot(slf: &Actor, wld: &World, p: &Plan) -> bool {
-    match _raycast(slf, slf.direction, wld, p, slf.level as u16 + 5) {
-        Some((team, _dist)) => team != slf.team,
-        None => false,
+pub fn score_shoot(slf: &Actor, wld: &World, p: &Plan) -> i32 {
+    match _raycast(slf, slf.direction, wld, p, reach_of(slf, "shoot")) {
+        Some((team, _dist)) if p.is_hostile(slf.team, team) => _weigh_by_mana(slf, "shoot"),
+        _ => 0,
     }
 }
-pub fn shoot(slf: &mut Actor, wld: &World, p: &Plan, spawn: &mut Vec<Actor>) {
-    passive_effect!(passive_aim => slf, wld, p);
+pub fn shoot(slf: &mut Actor, wld: &mut World, p: &Plan, spawn: &mut Vec<Actor>) {
+    if slf.target.is_none() {
+        passive_effect!(passive_aim => slf, wld, p);
+    }
     if slf.momentum > 0 {
         slf.log_action("steadied my aim.");
         return slf.momentum = 0;
     }
-    let mut shot = Actor::new(50, slf.level + 10, slf.team, slf.pos);
-    slf.act_exert(2, &format!("released {}.", shot.name));
+    let mut shot = _launch(
+        slf,
+        spawns("shoot"),
+        slf.level + magnitude("shoot"),
+        slf.direction,
+    );
+    slf.act_exert(cost("shoot"), &format!("released {}.", shot.name));
     shot.glyph = match slf.direction {
         0 | 4 => '|',
         2 | 6 => '-',
         1 | 5 => '/',
         _ => '\\',
     };
-    shot.direction = slf.direction;
     spawn.push(shot);
 }
 
 pub fn can_barrage(slf: &Actor, _wld: &World, _p: &Plan) -> bool {
-    slf.mana >= 6
+    slf.mana >= cost("barrage")
 }
-pub fn should_barrage(slf: &Actor, wld: &World, p: &Plan) -> bool {
-    p.is_near_enemy(slf.pos, slf.team) && should_shoot(slf, wld, p)
+pub fn score_barrage(slf: &Actor, wld: &World, p: &Plan) -> i32 {
+    if !p.is_near_enemy(slf.pos, slf.team) || score_shoot(slf, wld, p) == 0 {
+        return 0;
+    }
+    _weigh_by_mana(slf, "barrage")
 }
-pub fn barrage(slf: &mut Actor, wld: &World, p: &Plan, spawn: &mut Vec<Actor>) {
+pub fn barrage(slf: &mut Actor, wld: &mut World, p: &Plan, spawn: &mut Vec<Actor>) {
     if slf.momentum > 0 {
         slf.log_action("steadied my aim.");
         return slf.momentum = 0;
     }
-    slf.direction = (slf.direction + 7) % 8;
-    for _arrow in 0..3 {
+    let arrows = magnitude("barrage");
+    slf.direction = (slf.direction + 8 - (arrows / 2) as u8 % 8) % 8;
+    for _arrow in 0..arrows {
         shoot(slf, wld, p, spawn);
         slf.direction = (slf.direction + 1) % 8;
     }
 }
 
 pub fn can_boomerang(slf: &Actor, _wld: &World, _p: &Plan) -> bool {
-    slf.mana >= 10
+    slf.mana >= cost("boomerang")
 }
-pub fn should_boomerang(slf: &Actor, _wld: &World, p: &Plan) -> bool {
-    p.num_enemies() > 5 && p.is_near_enemy(slf.pos, slf.team) && rand_int(60) == 0
+pub fn score_boomerang(slf: &Actor, _wld: &World, p: &Plan) -> i32 {
+    if p.num_enemies() <= 5 || !p.is_near_enemy(slf.pos, slf.team) {
+        return 0;
+    }
+    _weigh_by_mana(slf, "boomerang")
 }
-pub fn boomerang(slf: &mut Actor, _wld: &World, _p: &Plan, spawn: &mut Vec<Actor>) {
-    slf.act_exert(10, "threw a boomerang.");
-    let mut boomerang = Actor::new(53, slf.level, slf.team, slf.pos);
-    boomerang.direction = (slf.direction + 7) % 8;
-    boomerang.momentum = 100;
-    spawn.push(boomerang);
+pub fn boomerang(slf: &mut Actor, _wld: &mut World, _p: &Plan, spawn: &mut Vec<Actor>) {
+    slf.act_exert(cost("boomerang"), "threw a boomerang.");
+    spawn.push(_launch(
+        slf,
+        spawns("boomerang"),
+        slf.level + magnitude("boomerang"),
+        slf.direction,
+    ));
 }
 
 pub fn can_starburst(slf: &Actor, _wld: &World, _p: &Plan) -> bool {
-    slf.mana >= 20
+    slf.mana >= cost("starburst")
 }
-pub fn should_starburst(slf: &Actor, _wld: &World, p: &Plan) -> bool {
-    !slf.is_hurt() && p.distance_to_goal(slf.pos, slf.team) < 5
+pub fn score_starburst(slf: &Actor, _wld: &World, p: &Plan) -> i32 {
+    if slf.is_hurt() || p.distance_to_goal(slf.pos, slf.team) >= engage("starburst") {
+        return 0;
+    }
+    _weigh_by_mana(slf, "starburst")
 }
-pub fn starburst(slf: &mut Actor, _wld: &World, _p: &Plan, spawn: &mut Vec<Actor>) {
-    slf.act_exert(20, "unleashed fiery currents!");
+pub fn starburst(slf: &mut Actor, _wld: &mut World, _p: &Plan, spawn: &mut Vec<Actor>) {
+    slf.act_exert(cost("starburst"), "unleashed fiery currents!");
     for direction in 0..8 {
-        let mut blast = Actor::new(54, slf.level + 5, slf.team, slf.pos);
-        blast.direction = direction;
-        spawn.push(blast);
+        spawn.push(_launch(
+            slf,
+            spawns("starburst"),
+            slf.level + magnitude("starburst"),
+            direction,
+        ));
     }
 }
 
 pub fn can_blast(slf: &Actor, _wld: &World, _p: &Plan) -> bool {
-    slf.mana >= 2
+    slf.mana >= cost("blast")
 }
-pub fn should_blast(slf: &Actor, wld: &World, p: &Plan) -> bool {
-    !slf.is_hurt() && should_shoot(slf, wld, p)
+pub fn score_blast(slf: &Actor, wld: &World, p: &Plan) -> i32 {
+    if slf.is_hurt() || score_shoot(slf, wld, p) == 0 {
+        return 0;
+    }
+    _weigh_by_mana(slf, "blast")
 }
-pub fn blast(slf: &mut Actor, wld: &World, p: &Plan, spawn: &mut Vec<Actor>) {
-    passive_effect!(passive_aim => slf, wld, p);
-    slf.act_exert(2, "released an energy blast.");
-    let mut blast = Actor::new(51, slf.level + 5, slf.team, slf.pos);
-    blast.direction = slf.direction;
-    spawn.push(blast);
+pub fn blast(slf: &mut Actor, wld: &mut World, p: &Plan, spawn: &mut Vec<Actor>) {
+    if slf.target.is_none() {
+        passive_effect!(passive_aim => slf, wld, p);
+    }
+    slf.act_exert(cost("blast"), "released an energy blast.");
+    spawn.push(_launch(
+        slf,
+        spawns("blast"),
+        slf.level + magnitude("blast"),
+        slf.direction,
+    ));
+}
+
+pub fn can_fireball(slf: &Actor, _wld: &World, _p: &Plan) -> bool {
+    slf.mana >= cost("fireball")
+}
+fn _fireball_at(slf: &Actor, center: (u16, u16)) -> Effect {
+    let damage = magnitude("fireball") * (slf.level + 5);
+    let mut fireball = Effect::new(EFFECT_EXPLOSION, center, radius("fireball"), damage);
+    fireball.owner = Some(slf.uid);
+    fireball
+}
+pub fn score_fireball(slf: &Actor, wld: &World, p: &Plan) -> i32 {
+    let center = _impact(slf, wld, p, reach("fireball"));
+    _score_area(slf, &_fireball_at(slf, center), wld, p, "fireball")
+}
+pub fn fireball(slf: &mut Actor, wld: &mut World, p: &Plan, _spawn: &mut Vec<Actor>) {
+    slf.act_exert(cost("fireball"), "hurled a fireball!");
+    let center = match slf.target {
+        Some(target) => target,
+        None => _impact(slf, wld, p, reach("fireball")),
+    };
+    wld.add_effect(_fireball_at(slf, center));
 }
 
 pub fn can_teleport(slf: &Actor, _wld: &World, _p: &Plan) -> bool {
-    slf.mana >= 3
+    slf.mana >= cost("teleport")
 }
-pub fn should_teleport(slf: &Actor, _wld: &World, p: &Plan) -> bool {
-    p.is_near_enemy(slf.pos, slf.team) && slf.health < slf.max_health() / 2
+pub fn score_teleport(slf: &Actor, _wld: &World, p: &Plan) -> i32 {
+    if p.is_near_enemy(slf.pos, slf.team) && slf.is_hurt() {
+        return weight("teleport");
+    }
+    0
 }
 pub fn teleport(slf: &mut Actor, wld: &mut World, p: &Plan, _spawn: &mut Vec<Actor>) {
-    slf.act_exert(3, "teleported.");
+    slf.act_exert(cost("teleport"), "teleported.");
+    if let Some(target) = slf.target {
+        let in_reach = wld.line(slf.pos, target).len() <= usize::from(reach("teleport"));
+        if in_reach
+            && wld.is_in_view(slf.pos, target)
+            && p.whos_at(target).is_none()
+            && !slf.walls().contains(wld.glyph_at(target))
+        {
+            return slf.pos = target;
+        }
+    }
     if let Some(teleport_marker) = slf
         .inventory
         .iter()
@@ -350,76 +596,118 @@ pub fn teleport(slf: &mut Actor, wld: &mut World, p: &Plan, _spawn: &mut Vec<Act
 }
 
 pub fn can_teleport_marker(slf: &Actor, _wld: &World, _p: &Plan) -> bool {
-    slf.mana >= 10
+    slf.mana >= cost("teleport_marker")
 }
-pub fn should_teleport_marker(_slf: &Actor, _wld: &World, _p: &Plan) -> bool {
-    false
+pub fn score_teleport_marker(_slf: &Actor, _wld: &World, _p: &Plan) -> i32 {
+    0
 }
-pub fn teleport_marker(slf: &mut Actor, wld: &mut World, _p: &Plan, _s: &mut Vec<Actor>) {
+pub fn teleport_marker(slf: &mut Actor, wld: &mut World, _p: &Plan, _spawn: &mut Vec<Actor>) {
     slf.log_action("conjured a strange glyph.");
     let pos = wld.offset(slf.pos, slf.direction);
     wld.add_item(Item::new(ITEM_TELEPORT_MARKER, slf.level, slf.team), pos);
 }
 
 pub fn can_heal(slf: &Actor, _wld: &World, _p: &Plan) -> bool {
-    slf.mana >= 5
-}
-pub fn should_heal(slf: &Actor, wld: &World, p: &Plan) -> bool {
-    match _raycast(slf, slf.direction, wld, p, 2) {
-        Some((team, _dist)) => !slf.is_hurt() && team == slf.team && p.num_enemies() != 0,
-        None => false,
+    slf.mana >= cost("heal")
+}
+pub fn score_heal(slf: &Actor, wld: &World, p: &Plan) -> i32 {
+    let hurt_allies = (0..8)
+        .map(|dir| wld.offset(slf.pos, dir))
+        .filter(|&pos| match p.whos_at(pos) {
+            Some(&team) => p.is_allied(slf.team, team) && p.is_wounded_at(pos),
+            None => false,
+        })
+        .count();
+    _weigh_by_mana(slf, "heal") * hurt_allies as i32
+}
+pub fn heal(slf: &mut Actor, _wld: &mut World, _p: &Plan, spawn: &mut Vec<Actor>) {
+    slf.act_exert(cost("heal"), "released a healing current.");
+    if slf.target.is_some() {
+        // a focused current is twice as strong
+        let healing_current = _launch(slf, spawns("heal"), magnitude("heal") * 2, slf.direction);
+        return spawn.push(healing_current);
     }
-}
-pub fn heal(slf: &mut Actor, _ww: &World, _p: &Plan, spawn: &mut Vec<Actor>) {
-    slf.act_exert(5, "released a healing current.");
     for direction in 0..8 {
-        let mut healing_current = Actor::new(52, 4, slf.team, slf.pos);
-        healing_current.direction = direction;
-        spawn.push(healing_current);
+        // healing currents have the same strength whoever releases them
+        spawn.push(_launch(slf, spawns("heal"), magnitude("heal"), direction));
     }
 }
 
 pub fn can_lie(_slf: &Actor, _wld: &World, _p: &Plan) -> bool {
     true
 }
-pub fn should_lie(slf: &Actor, _wld: &World, p: &Plan) -> bool {
-    slf.team != 0 && p.distance_to_goal(slf.pos, slf.team) > 10
+pub fn score_lie(slf: &Actor, _wld: &World, p: &Plan) -> i32 {
+    if slf.team != 0 && p.distance_to_goal(slf.pos, slf.team) > engage("lie") {
+        return weight("lie");
+    }
+    0
 }
-pub fn lie(slf: &mut Actor, _wld: &World, _p: &Plan, _spawn: &mut Vec<Actor>) {
+pub fn lie(slf: &mut Actor, _wld: &mut World, _p: &Plan, _spawn: &mut Vec<Actor>) {
     slf.log_action("crumpled to the ground.");
-    slf.stun(10 + rand_int(1) as i16);
+    slf.stun((magnitude("lie") + rand_int(1)) as i16);
     slf.recover_fully();
 }
 
 pub fn can_summon_faerie(slf: &Actor, _wld: &World, _p: &Plan) -> bool {
-    slf.mana >= 5
+    slf.mana >= cost("summon_faerie") && _can_summon(slf, "summon_faerie")
 }
-pub fn should_summon_faerie(slf: &Actor, _wld: &World, p: &Plan) -> bool {
-    p.is_near_enemy(slf.pos, slf.team)
+pub fn score_summon_faerie(slf: &Actor, _wld: &World, p: &Plan) -> i32 {
+    if !p.is_near_enemy(slf.pos, slf.team) {
+        return 0;
+    }
+    _weigh_by_mana(slf, "summon_faerie")
 }
-pub fn summon_faerie(slf: &mut Actor, _wld: &World, _p: &Plan, spawn: &mut Vec<Actor>) {
-    slf.act_exert(5, "called a faerie.");
-    let mut faerie = Actor::new(55, slf.level + 5, slf.team, slf.pos);
+pub fn summon_faerie(slf: &mut Actor, _wld: &mut World, _p: &Plan, spawn: &mut Vec<Actor>) {
+    slf.act_exert(cost("summon_faerie"), "called a faerie.");
+    let level = slf.level + magnitude("summon_faerie");
+    let pos = slf.pos;
+    let mut faerie = _summon(slf, "summon_faerie", spawns("summon_faerie"), level, pos);
     faerie.direction = slf.direction;
     spawn.push(faerie);
 }
 
+pub fn can_miasma(slf: &Actor, _wld: &World, _p: &Plan) -> bool {
+    slf.mana >= cost("miasma")
+}
+fn _miasma(slf: &Actor, wld: &World) -> Effect {
+    let mut center = slf.pos;
+    for _step in 0..reach("miasma") {
+        center = wld.offset(center, slf.direction);
+    }
+    let mut cloud = Effect::new(EFFECT_CLOUD, center, radius("miasma"), magnitude("miasma"));
+    cloud.turns = 5 * slf.move_lag(); // five of the caster's turns
+    cloud.owner = Some(slf.uid);
+    cloud
+}
+pub fn score_miasma(slf: &Actor, wld: &World, p: &Plan) -> i32 {
+    _score_area(slf, &_miasma(slf, wld), wld, p, "miasma")
+}
+pub fn miasma(slf: &mut Actor, wld: &mut World, _p: &Plan, _spawn: &mut Vec<Actor>) {
+    slf.act_exert(cost("miasma"), "exhaled a foul cloud.");
+    let cloud = _miasma(slf, wld);
+    wld.add_effect(cloud);
+}
+
 pub fn can_grow_tree(slf: &Actor, _wld: &World, _p: &Plan) -> bool {
-    slf.mana >= 6
+    slf.mana >= cost("grow_tree")
 }
-pub fn should_grow_tree(slf: &Actor, _wld: &World, p: &Plan) -> bool {
-    (p.is_defending(slf.team) && p.distance_to_goal(slf.pos, slf.team) < 3)
+pub fn score_grow_tree(slf: &Actor, _wld: &World, p: &Plan) -> i32 {
+    if (p.is_defending(slf.team) && p.distance_to_goal(slf.pos, slf.team) < engage("grow_tree"))
         || (p.is_retreating(slf.team) && p.distance_to_goal(slf.pos, slf.team) > 20)
+    {
+        return weight("grow_tree");
+    }
+    0
 }
-pub fn grow_tree(slf: &mut Actor, wld: &mut World, p: &Plan, _spawn: &mut Vec<Actor>) {
+pub fn grow_tree(slf: &mut Actor, wld: &mut World, _p: &Plan, _spawn: &mut Vec<Actor>) {
     for dir in &[0, 7, 1, 6, 2, 5, 3] {
-        if !can_grow_tree(slf, wld, p) {
+        if slf.mana < cost("grow_tree") {
             break;
         }
-        let pos = wld.neighbor(slf.pos, (slf.direction + dir) % 8, slf.team, &slf.walls);
+        let pos = wld.neighbor(slf.pos, (slf.direction + dir) % 8, slf.team, &slf.walls());
         if pos != slf.pos {
-            slf.act_exert(6, "grew a tree.");
-            wld.add_item(Item::new(100, slf.level, slf.team), pos);
+            slf.act_exert(cost("grow_tree"), "grew a tree.");
+            wld.add_item(Item::new(spawns("grow_tree"), slf.level, slf.team), pos);
         }
     }
 }
@@ -427,65 +715,93 @@ pub fn grow_tree(slf: &mut Actor, wld: &mut World, p: &Plan, _spawn: &mut Vec<Ac
 pub fn can_expand(slf: &Actor, _wld: &World, _p: &Plan) -> bool {
     slf.health == slf.max_health()
 }
-pub fn should_expand(_slf: &Actor, _wld: &World, _p: &Plan) -> bool {
-    rand_int(5) == 0
+pub fn score_expand(slf: &Actor, _wld: &World, p: &Plan) -> i32 {
+    // blobs grow now and then, 1 time in 5, but not while enemies are near:
+    if p.is_near_enemy(slf.pos, slf.team) || rand_int(5) != 0 {
+        return 0;
+    }
+    weight("expand")
 }
-pub fn expand(slf: &mut Actor, _wld: &World, _p: &Plan, _spawn: &mut Vec<Actor>) {
+pub fn expand(slf: &mut Actor, _wld: &mut World, _p: &Plan, _spawn: &mut Vec<Actor>) {
     let new_kind = slf.kind + 1;
     slf.initialize(new_kind);
     slf.health = slf.max_health() / 2;
 }
 
 pub fn can_multiply(slf: &Actor, _wld: &World, _p: &Plan) -> bool {
-    slf.mana == slf.max_mana() && slf.health == slf.max_health()
+    slf.mana == slf.max_mana() && slf.health == slf.max_health() && _can_summon(slf, "multiply")
 }
-pub fn should_multiply(_slf: &Actor, _wld: &World, p: &Plan) -> bool {
-    p.num_enemies() < 300
+pub fn score_multiply(_slf: &Actor, _wld: &World, p: &Plan) -> i32 {
+    if p.num_enemies() < 300 {
+        return weight("multiply");
+    }
+    0
 }
-pub fn multiply(slf: &mut Actor, wld: &World, _p: &Plan, spawn: &mut Vec<Actor>) {
-    slf.initialize(8);
+pub fn multiply(slf: &mut Actor, wld: &mut World, _p: &Plan, spawn: &mut Vec<Actor>) {
+    let kind = spawns("multiply");
+    slf.initialize(kind);
     slf.health = slf.max_health() / 2;
-    let pos = wld.neighbor(slf.pos, slf.direction, slf.team, &slf.walls);
-    let mut new_spawn = Actor::new(8, slf.level, slf.team, pos);
+    let pos = wld.neighbor(slf.pos, slf.direction, slf.team, &slf.walls());
+    let level = slf.level;
+    let mut new_spawn = _summon(slf, "multiply", kind, level, pos);
     new_spawn.health /= 2;
     spawn.push(new_spawn);
 }
 
-pub fn can_spawn_elf(_slf: &Actor, _wld: &World, _p: &Plan) -> bool {
-    true
+// Generators spawn at a steady rate, more eagerly with enemies nearby.
+fn _score_spawn(skill: &str, slf: &Actor, p: &Plan) -> i32 {
+    let rate = if p.is_near_enemy(slf.pos, slf.team) {
+        20
+    } else {
+        50
+    };
+    if rand_int(rate) == 0 {
+        return weight(skill);
+    }
+    0
+}
+
+pub fn can_spawn_elf(slf: &Actor, _wld: &World, _p: &Plan) -> bool {
+    _can_summon(slf, "spawn_elf")
 }
-pub fn should_spawn_elf(_slf: &Actor, _wld: &World, _p: &Plan) -> bool {
-    rand_int(50) == 0
+pub fn score_spawn_elf(slf: &Actor, _wld: &World, p: &Plan) -> i32 {
+    _score_spawn("spawn_elf", slf, p)
 }
-pub fn spawn_elf(slf: &Actor, _wld: &World, _p: &Plan, spawn: &mut Vec<Actor>) {
-    spawn.push(Actor::new(1, slf.level, slf.team, slf.pos));
+pub fn spawn_elf(slf: &mut Actor, _wld: &mut World, _p: &Plan, spawn: &mut Vec<Actor>) {
+    let (level, pos) = (slf.level, slf.pos);
+    let summoned = _summon(slf, "spawn_elf", spawns("spawn_elf"), level, pos);
+    spawn.push(summoned);
 }
 
-pub fn can_spawn_dead(_slf: &Actor, _wld: &World, _p: &Plan) -> bool {
-    true
+pub fn can_spawn_dead(slf: &Actor, _wld: &World, _p: &Plan) -> bool {
+    _can_summon(slf, "spawn_dead")
 }
-pub fn should_spawn_dead(_slf: &Actor, _wld: &World, _p: &Plan) -> bool {
-    rand_int(50) == 0
+pub fn score_spawn_dead(slf: &Actor, _wld: &World, p: &Plan) -> i32 {
+    _score_spawn("spawn_dead", slf, p)
 }
-pub fn spawn_dead(slf: &Actor, _wld: &World, _p: &Plan, spawn: &mut Vec<Actor>) {
-    spawn.push(Actor::new(4, slf.level, slf.team, slf.pos));
+pub fn spawn_dead(slf: &mut Actor, _wld: &mut World, _p: &Plan, spawn: &mut Vec<Actor>) {
+    let (level, pos) = (slf.level, slf.pos);
+    let summoned = _summon(slf, "spawn_dead", spawns("spawn_dead"), level, pos);
+    spawn.push(summoned);
 }
 
-pub fn can_spawn_mage(_slf: &Actor, _wld: &World, _p: &Plan) -> bool {
-    true
+pub fn can_spawn_mage(slf: &Actor, _wld: &World, _p: &Plan) -> bool {
+    _can_summon(slf, "spawn_mage")
 }
-pub fn should_spawn_mage(_slf: &Actor, _wld: &World, _p: &Plan) -> bool {
-    rand_int(50) == 0
+pub fn score_spawn_mage(slf: &Actor, _wld: &World, p: &Plan) -> i32 {
+    _score_spawn("spawn_mage", slf, p)
 }
-pub fn spawn_mage(slf: &Actor, _wld: &World, _p: &Plan, spawn: &mut Vec<Actor>) {
-    spawn.push(Actor::new(3, slf.level, slf.team, slf.pos));
+pub fn spawn_mage(slf: &mut Actor, _wld: &mut World, _p: &Plan, spawn: &mut Vec<Actor>) {
+    let (level, pos) = (slf.level, slf.pos);
+    let summoned = _summon(slf, "spawn_mage", spawns("spawn_mage"), level, pos);
+    spawn.push(summoned);
 }
 
 pub fn can_pick(slf: &Actor, _wld: &World, _p: &Plan) -> bool {
     slf.mana >= slf.max_mana()
 }
-pub fn should_pick(_slf: &Actor, _wld: &World, _p: &Plan) -> bool {
-    false
+pub fn score_pick(_slf: &Actor, _wld: &World, _p: &Plan) -> i32 {
+    0
 }
 pub fn pick(slf: &mut Actor, wld: &mut World, p: &Plan, _spawn: &mut Vec<Actor>) {
     let door_pos = wld.offset(slf.pos, slf.direction);
@@ -494,6 +810,9 @@ pub fn pick(slf: &mut Actor, wld: &mut World, p: &Plan, _spawn: &mut Vec<Actor>)
         if item.kind == ITEM_DOOR {
             item.initialize_as(ITEM_DOOR_OPEN);
             return slf.act_exert(cost, "picked the lock.");
+        } else if item.kind == ITEM_CHEST_LOCKED {
+            item.initialize_as(ITEM_CHEST);
+            return slf.act_exert(cost, "picked the lock on the chest.");
         } else if item.kind == ITEM_DOOR_OPEN && p.whos_at(door_pos).is_none() {
             item.initialize_as(ITEM_DOOR);
             item.team = slf.team;
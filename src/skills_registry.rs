@@ -1,56 +1,461 @@
-// DON'T EDIT: automatically generated by ../scripts/make_skills_registry.sh
+// Handles looking up skills by name, and picking which skill an actor uses.
 use actor::Actor;
+use constants::TARGET_NONE;
+use csv;
 use plan::Plan;
+use skills;
 use skills::*;
+use std::cell::RefCell;
+use std::error::Error;
+use std::rc::Rc;
 use world::World;
 
-#[allow(warnings)]
-pub fn choose_skill(actor: &mut Actor, wld: &World, p: &Plan) -> bool {
-    choose_skill!(should_sprint if can_sprint => actor, wld, p);
-    choose_skill!(should_charge if can_charge => actor, wld, p);
-    choose_skill!(should_leap if can_leap => actor, wld, p);
-    choose_skill!(should_cloak if can_cloak => actor, wld, p);
-    choose_skill!(should_shoot if can_shoot => actor, wld, p);
-    choose_skill!(should_barrage if can_barrage => actor, wld, p);
-    choose_skill!(should_boomerang if can_boomerang => actor, wld, p);
-    choose_skill!(should_starburst if can_starburst => actor, wld, p);
-    choose_skill!(should_blast if can_blast => actor, wld, p);
-    choose_skill!(should_teleport if can_teleport => actor, wld, p);
-    choose_skill!(should_teleport_marker if can_teleport_marker => actor, wld, p);
-    choose_skill!(should_heal if can_heal => actor, wld, p);
-    choose_skill!(should_lie if can_lie => actor, wld, p);
-    choose_skill!(should_summon_faerie if can_summon_faerie => actor, wld, p);
-    choose_skill!(should_grow_tree if can_grow_tree => actor, wld, p);
-    choose_skill!(should_expand if can_expand => actor, wld, p);
-    choose_skill!(should_multiply if can_multiply => actor, wld, p);
-    choose_skill!(should_spawn_elf if can_spawn_elf => actor, wld, p);
-    choose_skill!(should_spawn_dead if can_spawn_dead => actor, wld, p);
-    choose_skill!(should_spawn_mage if can_spawn_mage => actor, wld, p);
-    choose_skill!(should_pick if can_pick => actor, wld, p);
-    false
-}
-
-#[allow(warnings)]
+pub trait Skill {
+    fn name(&self) -> &str;
+    fn description(&self) -> String;
+    /// Turns before the actor can use the skill again.
+    fn cooldown(&self) -> u16 {
+        0
+    }
+    /// Turns the actor spends preparing the skill before it takes effect.
+    fn windup(&self) -> u16 {
+        0
+    }
+    /// What the player picks with the targeting cursor (e.g. a direction or cell).
+    fn targeting(&self) -> u8 {
+        TARGET_NONE
+    }
+    /// How far the actor can aim the skill (0 means anywhere in sight).
+    fn reach(&self, _actor: &Actor) -> u16 {
+        0
+    }
+    fn can_use(&self, actor: &Actor, world: &World, plan: &Plan) -> bool;
+    /// How useful the skill is right now, on the same scale as moves (0 is useless).
+    fn score(&self, actor: &Actor, world: &World, plan: &Plan) -> i32;
+    fn use_skill(&self, actor: &mut Actor, world: &mut World, plan: &Plan, spawn: &mut Vec<Actor>);
+}
+
+type CanUse = fn(&Actor, &World, &Plan) -> bool;
+type Score = fn(&Actor, &World, &Plan) -> i32;
+type UseSkill = fn(&mut Actor, &mut World, &Plan, &mut Vec<Actor>);
+
+/// A skill made from the plain functions in the skills module, with its
+/// parameters read from the skill config.
+pub struct Builtin {
+    name: &'static str,
+    can_use: CanUse,
+    score: Score,
+    use_skill: UseSkill,
+}
+
+impl Builtin {
+    pub fn new(name: &'static str, can_use: CanUse, score: Score, use_skill: UseSkill) -> Builtin {
+        Builtin {
+            name,
+            can_use,
+            score,
+            use_skill,
+        }
+    }
+}
+
+impl Skill for Builtin {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn description(&self) -> String {
+        skills::description(self.name)
+    }
+
+    fn cooldown(&self) -> u16 {
+        skills::cooldown(self.name)
+    }
+
+    fn windup(&self) -> u16 {
+        skills::windup(self.name)
+    }
+
+    fn targeting(&self) -> u8 {
+        skills::targeting(self.name)
+    }
+
+    fn reach(&self, actor: &Actor) -> u16 {
+        skills::reach_of(actor, self.name)
+    }
+
+    fn can_use(&self, actor: &Actor, world: &World, plan: &Plan) -> bool {
+        (self.can_use)(actor, world, plan)
+    }
+
+    fn score(&self, actor: &Actor, world: &World, plan: &Plan) -> i32 {
+        (self.score)(actor, world, plan)
+    }
+
+    fn use_skill(&self, actor: &mut Actor, world: &mut World, plan: &Plan, spawn: &mut Vec<Actor>) {
+        (self.use_skill)(actor, world, plan, spawn)
+    }
+}
+
+#[derive(Default)]
+pub struct SkillRegistry {
+    skills: Vec<Rc<dyn Skill>>,
+    passives: Vec<(String, String)>,
+}
+
+impl SkillRegistry {
+    pub fn with_builtin_skills() -> SkillRegistry {
+        let mut registry = SkillRegistry::default();
+        for skill in _builtin_skills() {
+            registry.register(skill);
+        }
+        for &name in _BUILTIN_PASSIVES {
+            registry.register_passive(name, &skills::description(name));
+        }
+        registry
+    }
+
+    /// Adds a skill, replacing any existing skill with the same name.
+    pub fn register(&mut self, skill: Rc<dyn Skill>) {
+        self.skills.retain(|old| old.name() != skill.name());
+        self.skills.push(skill);
+    }
+
+    /// Passive skills are hooked directly into actors; this makes them known.
+    pub fn register_passive(&mut self, name: &str, description: &str) {
+        self.passives.retain(|old| old.0 != name);
+        self.passives
+            .push((name.to_owned(), description.to_owned()));
+    }
+
+    pub fn get(&self, name: &str) -> Option<Rc<dyn Skill>> {
+        self.skills
+            .iter()
+            .find(|skill| skill.name() == name)
+            .cloned()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        let actives = self.skills.iter().map(|skill| skill.name().to_owned());
+        actives
+            .chain(self.passives.iter().map(|passive| passive.0.clone()))
+            .collect()
+    }
+
+    pub fn describe(&self, name: &str) -> Option<String> {
+        if let Some(skill) = self.get(name) {
+            return Some(skill.description());
+        }
+        self.passives
+            .iter()
+            .find(|passive| passive.0 == name)
+            .map(|passive| passive.1.clone())
+    }
+
+    /// Checks that every skill named in the actor config has been registered.
+    pub fn validate(&self, config: &str) -> Result<(), Box<dyn Error>> {
+        let names = self.names();
+        let mut unknown = Vec::new();
+        for record in csv::Reader::from_path(config)?.records() {
+            let record = record?;
+            for skill in record.get(5).unwrap_or("").split_whitespace() {
+                if !names.iter().any(|name| name == skill) {
+                    unknown.push(format!("{} (kind {})", skill, record.get(0).unwrap_or("?")));
+                }
+            }
+        }
+        if !unknown.is_empty() {
+            return Err(format!("Unknown skills in {}: {}", config, unknown.join(", ")).into());
+        }
+        Ok(())
+    }
+}
+
+thread_local!(static _SKILL_REGISTRY: RefCell<SkillRegistry> = RefCell::new(SkillRegistry::with_builtin_skills()));
+
+/// Makes a new skill (e.g. from another crate) available to actors by name.
+pub fn register(skill: Rc<dyn Skill>) {
+    _SKILL_REGISTRY.with(|registry| registry.borrow_mut().register(skill))
+}
+
+pub fn lookup(name: &str) -> Option<Rc<dyn Skill>> {
+    _SKILL_REGISTRY.with(|registry| registry.borrow().get(name))
+}
+
+pub fn names() -> Vec<String> {
+    _SKILL_REGISTRY.with(|registry| registry.borrow().names())
+}
+
+pub fn describe(name: &str) -> Option<String> {
+    _SKILL_REGISTRY.with(|registry| registry.borrow().describe(name))
+}
+
+pub fn validate(config: &str) -> Result<(), Box<dyn Error>> {
+    _SKILL_REGISTRY.with(|registry| registry.borrow().validate(config))
+}
+
+/// The actor's most useful skill right now and its score.
+pub fn choose_skill(actor: &Actor, wld: &World, p: &Plan) -> (i32, String) {
+    let mut best = (0, String::new());
+    for skill in actor.skills.iter().filter_map(|name| lookup(name)) {
+        if actor.cooldown(skill.name()) == 0 && skill.can_use(actor, wld, p) {
+            let utility = skill.score(actor, wld, p);
+            if utility > best.0 {
+                best = (utility, skill.name().to_owned());
+            }
+        }
+    }
+    best
+}
+
+/// Starts using the selected skill, winding it up first if it needs to.
 pub fn use_skill(actor: &mut Actor, wld: &mut World, p: &Plan, spawn: &mut Vec<Actor>) {
-    use_skill!(sprint if can_sprint => actor, wld, p, spawn);
-    use_skill!(charge if can_charge => actor, wld, p, spawn);
-    use_skill!(leap if can_leap => actor, wld, p, spawn);
-    use_skill!(cloak if can_cloak => actor, wld, p, spawn);
-    use_skill!(shoot if can_shoot => actor, wld, p, spawn);
-    use_skill!(barrage if can_barrage => actor, wld, p, spawn);
-    use_skill!(boomerang if can_boomerang => actor, wld, p, spawn);
-    use_skill!(starburst if can_starburst => actor, wld, p, spawn);
-    use_skill!(blast if can_blast => actor, wld, p, spawn);
-    use_skill!(teleport if can_teleport => actor, wld, p, spawn);
-    use_skill!(teleport_marker if can_teleport_marker => actor, wld, p, spawn);
-    use_skill!(heal if can_heal => actor, wld, p, spawn);
-    use_skill!(lie if can_lie => actor, wld, p, spawn);
-    use_skill!(summon_faerie if can_summon_faerie => actor, wld, p, spawn);
-    use_skill!(grow_tree if can_grow_tree => actor, wld, p, spawn);
-    use_skill!(expand if can_expand => actor, wld, p, spawn);
-    use_skill!(multiply if can_multiply => actor, wld, p, spawn);
-    use_skill!(spawn_elf if can_spawn_elf => actor, wld, p, spawn);
-    use_skill!(spawn_dead if can_spawn_dead => actor, wld, p, spawn);
-    use_skill!(spawn_mage if can_spawn_mage => actor, wld, p, spawn);
-    use_skill!(pick if can_pick => actor, wld, p, spawn);
+    let name = actor.selected_skill();
+    if let Some(skill) = lookup(&name) {
+        if actor.cooldown(&name) > 0 {
+            actor.target = None;
+            return actor.log_action(&format!("wasn't ready to {} again.", name));
+        } else if !skill.can_use(actor, wld, p) {
+            actor.target = None;
+            return actor.log_action("was too tired!");
+        } else if skill.windup() > 0 {
+            actor.log_action(&format!("began to {}...", name));
+            return actor.wind_up(&name, skill.windup());
+        }
+        release_skill(actor, wld, p, spawn);
+    }
+}
+
+/// Puts the selected skill into effect and starts its cooldown; the target
+/// is used up either way.
+pub fn release_skill(actor: &mut Actor, wld: &mut World, p: &Plan, spawn: &mut Vec<Actor>) {
+    let name = actor.selected_skill();
+    if let Some(skill) = lookup(&name) {
+        if !skill.can_use(actor, wld, p) {
+            actor.target = None;
+            return actor.log_action("was too tired!");
+        }
+        if let Some(target) = actor.target {
+            if let Some(dir) = wld.direction_to(actor.pos, target) {
+                actor.direction = dir;
+            }
+        }
+        skill.use_skill(actor, wld, p, spawn);
+        actor.target = None;
+        actor.cool_down(&name, skill.cooldown());
+    }
+}
+
+const _BUILTIN_PASSIVES: &[&str] = &[
+    "passive_spin",
+    "passive_drift",
+    "passive_descend",
+    "passive_slam",
+    "passive_whirl",
+    "passive_trip",
+    "passive_backstab",
+    "passive_burn",
+    "passive_heal",
+    "passive_grow",
+    "passive_aim",
+    "passive_block",
+    "passive_counter",
+    "passive_displace",
+];
+
+fn _builtin_skills() -> Vec<Rc<dyn Skill>> {
+    let skills: Vec<Rc<dyn Skill>> = vec![
+        Rc::new(Builtin::new("sprint", can_sprint, score_sprint, sprint)),
+        Rc::new(Builtin::new("charge", can_charge, score_charge, charge)),
+        Rc::new(Builtin::new("cleave", can_cleave, score_cleave, cleave)),
+        Rc::new(Builtin::new("leap", can_leap, score_leap, leap)),
+        Rc::new(Builtin::new("cloak", can_cloak, score_cloak, cloak)),
+        Rc::new(Builtin::new("shoot", can_shoot, score_shoot, shoot)),
+        Rc::new(Builtin::new("barrage", can_barrage, score_barrage, barrage)),
+        Rc::new(Builtin::new(
+            "boomerang",
+            can_boomerang,
+            score_boomerang,
+            boomerang,
+        )),
+        Rc::new(Builtin::new(
+            "starburst",
+            can_starburst,
+            score_starburst,
+            starburst,
+        )),
+        Rc::new(Builtin::new("blast", can_blast, score_blast, blast)),
+        Rc::new(Builtin::new(
+            "fireball",
+            can_fireball,
+            score_fireball,
+            fireball,
+        )),
+        Rc::new(Builtin::new(
+            "teleport",
+            can_teleport,
+            score_teleport,
+            teleport,
+        )),
+        Rc::new(Builtin::new(
+            "teleport_marker",
+            can_teleport_marker,
+            score_teleport_marker,
+            teleport_marker,
+        )),
+        Rc::new(Builtin::new("heal", can_heal, score_heal, heal)),
+        Rc::new(Builtin::new("lie", can_lie, score_lie, lie)),
+        Rc::new(Builtin::new(
+            "summon_faerie",
+            can_summon_faerie,
+            score_summon_faerie,
+            summon_faerie,
+        )),
+        Rc::new(Builtin::new("miasma", can_miasma, score_miasma, miasma)),
+        Rc::new(Builtin::new(
+            "grow_tree",
+            can_grow_tree,
+            score_grow_tree,
+            grow_tree,
+        )),
+        Rc::new(Builtin::new("expand", can_expand, score_expand, expand)),
+        Rc::new(Builtin::new(
+            "multiply",
+            can_multiply,
+            score_multiply,
+            multiply,
+        )),
+        Rc::new(Builtin::new(
+            "spawn_elf",
+            can_spawn_elf,
+            score_spawn_elf,
+            spawn_elf,
+        )),
+        Rc::new(Builtin::new(
+            "spawn_dead",
+            can_spawn_dead,
+            score_spawn_dead,
+            spawn_dead,
+        )),
+        Rc::new(Builtin::new(
+            "spawn_mage",
+            can_spawn_mage,
+            score_spawn_mage,
+            spawn_mage,
+        )),
+        Rc::new(Builtin::new("pick", can_pick, score_pick, pick)),
+    ];
+    skills
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use constants;
+    use constants::ACT_WAIT;
+
+    struct Shout;
+
+    impl Skill for Shout {
+        fn name(&self) -> &str {
+            "shout"
+        }
+
+        fn description(&self) -> String {
+            "shout at the enemy".to_owned()
+        }
+
+        fn can_use(&self, _actor: &Actor, _world: &World, _plan: &Plan) -> bool {
+            true
+        }
+
+        fn score(&self, _actor: &Actor, _world: &World, _plan: &Plan) -> i32 {
+            1000
+        }
+
+        fn use_skill(&self, actor: &mut Actor, _wld: &mut World, _p: &Plan, _s: &mut Vec<Actor>) {
+            actor.log_action("shouted.");
+        }
+    }
+
+    #[test]
+    fn test_builtin_skills_cover_actor_config() {
+        let registry = SkillRegistry::with_builtin_skills();
+        registry.validate("config/glad/actor.csv").unwrap();
+        assert!(registry.describe("shoot").is_some());
+        assert!(registry.describe("passive_aim").is_some());
+        assert!(registry.describe("shout").is_none());
+        let thief = Actor::new(6, 3, 0, (0, 0));
+        assert_eq!(
+            skills::reach_of(&thief, "shoot"),
+            skills::reach("shoot") + 3
+        );
+        // the cursor reaches as far as the shot does:
+        let shoot = registry.get("shoot").unwrap();
+        assert_eq!(shoot.reach(&thief), skills::reach_of(&thief, "shoot"));
+    }
+
+    #[test]
+    fn test_register_and_use() {
+        let mut world = World::new("glad");
+        world.reshape((5, 5));
+        let plan = Plan::new((5, 5), &Default::default());
+        let mut actor = Actor::new(0, 1, 0, (2, 2));
+        actor.skills.push("shout".to_owned());
+        register(Rc::new(Shout));
+        assert!(lookup("shout").is_some());
+        assert!(names().contains(&"shout".to_owned()));
+        assert_eq!(choose_skill(&actor, &world, &plan).1, "shout");
+        actor.select_skill("shout");
+        use_skill(&mut actor, &mut world, &plan, &mut vec![]);
+        assert_eq!(actor.log.last().unwrap().1, "I shouted.");
+    }
+
+    #[test]
+    fn test_cooldown_and_windup() {
+        let mut world = World::new("glad");
+        world.reshape((5, 5));
+        let mut plan = Plan::new((5, 5), &[0].iter().cloned().collect());
+        let mut mage = Actor::new(3, 5, 0, (2, 2));
+        let mut spawn = vec![];
+        mage.select_skill("blast");
+        use_skill(&mut mage, &mut world, &plan, &mut spawn);
+        use_skill(&mut mage, &mut world, &plan, &mut spawn);
+        assert_eq!(spawn.len(), 1);
+        assert!(mage.cooldown("blast") > 0);
+        assert_eq!(choose_skill(&mage, &world, &plan).1, "");
+        mage.select_skill("teleport_marker");
+        use_skill(&mut mage, &mut world, &plan, &mut spawn);
+        assert!(mage.is_winding_up() && world.items.is_empty());
+        mage.act(ACT_WAIT, &mut world, &mut plan, &mut vec![], &mut spawn);
+        assert!(!mage.is_winding_up() && !world.items.is_empty());
+    }
+
+    #[test]
+    fn test_target() {
+        let mut world = World::new("glad");
+        world.reshape((5, 5));
+        let plan = Plan::new((5, 5), &[0].iter().cloned().collect());
+        let mut mage = Actor::new(3, 6, 0, (0, 0));
+        mage.select_skill("teleport");
+        assert_eq!(
+            lookup("teleport").unwrap().targeting(),
+            constants::TARGET_CELL
+        );
+        mage.target = Some((3, 2));
+        release_skill(&mut mage, &mut world, &plan, &mut vec![]);
+        assert_eq!(mage.pos, (3, 2));
+        assert!(mage.target.is_none());
+
+        // a skill that can't be used, or is interrupted, forgets its target:
+        mage.target = Some((1, 1));
+        use_skill(&mut mage, &mut world, &plan, &mut vec![]);
+        assert!(mage.target.is_none());
+        mage.select_skill("fireball");
+        mage.target = Some((1, 1));
+        use_skill(&mut mage, &mut world, &plan, &mut vec![]);
+        assert!(mage.is_winding_up() && mage.target.is_some());
+        mage.stun(1);
+        assert!(!mage.is_winding_up() && mage.target.is_none());
+    }
 }
@@ -1,8 +1,18 @@
 // Handling of the global game state.
 use actor::Actor;
+use constants::EFFECT_EXPLOSION;
+use constants::{ACT_GIVE, ACT_MOVES, ACT_SKILL, ACT_THROW, ACT_TRADE, ACT_USE, ACT_WAIT};
+use constants::{ITEM_CHEST_OPEN, ITEM_HEALING, ITEM_LIFE_GEM, ITEM_RESURRECTION, TARGET_NONE};
+use controller::{Ai, Human};
+use inflector::Inflector;
+use item::{self, Item};
+use item_effects;
 use plan::Plan;
-use std::cmp;
+use progression;
+use skills::rand_int;
+use skills_registry;
 use std::collections::{HashSet, VecDeque};
+use std::{cmp, mem};
 use view::View;
 use world::World;
 
@@ -15,17 +25,21 @@ pub struct State {
     pub world_desc: String,
     pub world_name: String,
     pub score: u32,
+    pub wallet: u32,        // the party's money, in silver pieces
+    pub fallen: Vec<Actor>, // teammates who died, waiting to be revived
     time: u32,
     autopilot: bool,
 
     pub actors: Vec<Actor>,
-    pub player_idx: usize,
+    pub player_idx: usize, // whose view is shown: the human whose turn it is
+    pub humans: usize,     // how many players share the keyboard, each with a character
     pub player_team: VecDeque<Actor>,
     pub team_idxs: HashSet<usize>,
     pub plan: Plan,
     pub view: View,
     spawn: Vec<Actor>,
 
+    config: String,
     create_team: fn(usize, usize) -> Vec<Actor>,
     setup_scenario: fn(&mut State),
 }
@@ -45,14 +59,18 @@ impl State {
             time: 1,
             autopilot: false,
             score: 0,
+            wallet: 0,
+            fallen: Vec::new(),
 
             actors: Vec::new(),
             player_idx: 0,
+            humans: 1,
             player_team: VecDeque::new(),
             team_idxs: HashSet::new(),
             plan: Plan::new((0, 0), &HashSet::new()),
             spawn: Vec::new(),
             view: View::new(),
+            config: config.to_owned(),
             create_team,
             setup_scenario,
         }
@@ -73,17 +91,130 @@ impl State {
         while self.world_idx != 0 {
             (self.setup_scenario)(self);
             self.plan = Plan::new(self.world.size, &self.team_idxs);
+            self.plan
+                .load_relations(&self.config, self.world_idx)
+                .unwrap();
             self.player_idx = 0;
             self.player_control_confirm();
             self.load_world_description();
             self.view.show();
             self.loop_turns();
+            if self.world_idx != 0 && !self.autopilot {
+                self.visit_shop();
+                self.visit_temple();
+                self.visit_stash();
+            }
             self.view.hide();
             self.actors.clear();
             self.team_idxs.clear();
         }
     }
 
+    // Between battles, the party can spend its money on supplies and healing.
+    fn visit_shop(&mut self) {
+        let mut wares = item::wares();
+        wares.push(Item::new(ITEM_HEALING, 1, 0));
+        while let Some(idx) = self.view.show_shop(self.wallet, &wares) {
+            let price = u32::from(wares[idx].price);
+            if price > self.wallet || self.player_team.is_empty() {
+                continue;
+            }
+            let names: Vec<String> = self.player_team.iter().map(|a| a.name.clone()).collect();
+            let buyer = match self.view.choose_from("Who gets it?", &names) {
+                Some(buyer) => buyer,
+                None => continue,
+            };
+            self.buy(&wares[idx], buyer);
+        }
+    }
+
+    fn buy(&mut self, ware: &Item, buyer: usize) {
+        self.wallet -= u32::from(ware.price);
+        let actor = &mut self.player_team[buyer];
+        if ware.kind == ITEM_HEALING {
+            actor.log_action("was tended to by a healer.");
+            item_effects::use_on_actor(actor, ware.kind);
+        } else {
+            actor.log_action(&format!("bought {}.", ware.name));
+            actor.add_to_inventory(Item::new(ware.kind, actor.level, actor.team));
+        }
+    }
+
+    // Between battles, fallen teammates can be brought back for a price.
+    fn visit_temple(&mut self) {
+        while !self.fallen.is_empty() && self.player_team.len() < TEAM_SIZE {
+            let offers: Vec<(&str, u32)> = self
+                .fallen
+                .iter()
+                .map(|a| (a.name.as_str(), _resurrection_price(a)))
+                .collect();
+            let idx = match self.view.show_temple(self.wallet, &offers) {
+                Some(idx) => idx,
+                None => return,
+            };
+            let price = offers[idx].1;
+            if price <= self.wallet && self.resurrect(idx) {
+                self.wallet -= price;
+            }
+        }
+    }
+
+    fn resurrect(&mut self, idx: usize) -> bool {
+        if self.player_team.len() >= TEAM_SIZE {
+            return false;
+        }
+        let mut actor = self.fallen.remove(idx);
+        let pos = actor.pos;
+        actor.revive(pos);
+        actor.recover_fully();
+        self.player_team.push_front(actor);
+        true
+    }
+
+    // Between battles, the party can pass its equipment around.
+    fn visit_stash(&mut self) {
+        loop {
+            let mut stash = Vec::new();
+            for (owner, actor) in self.player_team.iter().enumerate() {
+                for (item_idx, item) in actor.inventory.iter().enumerate() {
+                    if item.can_retain {
+                        stash.push((owner, item_idx));
+                    }
+                }
+            }
+            let entries: Vec<(&str, &Item)> = stash
+                .iter()
+                .map(|&(owner, item_idx)| {
+                    let actor = &self.player_team[owner];
+                    (actor.name.as_str(), &actor.inventory[item_idx])
+                })
+                .collect();
+            let choice = match self.view.show_stash(&entries) {
+                Some(choice) => choice,
+                None => return,
+            };
+            let names: Vec<String> = self.player_team.iter().map(|a| a.name.clone()).collect();
+            let taker = match self.view.choose_from("Who gets it?", &names) {
+                Some(taker) => taker,
+                None => continue,
+            };
+            let (owner, item_idx) = stash[choice];
+            self.hand_over(owner, item_idx, taker);
+        }
+    }
+
+    fn hand_over(&mut self, owner: usize, item_idx: usize, taker: usize) {
+        if owner == taker {
+            return;
+        }
+        let mut item = self.player_team[owner].inventory.remove(item_idx);
+        item.is_equipped = false;
+        let giver = self.player_team[owner].name.clone();
+        let actor = &mut self.player_team[taker];
+        actor.log_action(&format!("got {} from {}.", item.name, giver));
+        actor.add_to_inventory(item);
+    }
+
     fn load_world_description(&mut self) {
         let name = &self.world_name.clone();
         self.player_mut().log_event(&format!("[:{}:]", name), 0);
@@ -95,15 +226,30 @@ impl State {
 
     fn extract_team(&mut self, level_up: bool) {
         for mut actor in self.actors.drain(0..) {
+            if actor.is_persistent && actor.is_alive() {
+                for coin in actor.inventory.iter().filter(|item| item.is_currency()) {
+                    self.wallet += u32::from(coin.price);
+                }
+            }
             actor.inventory.retain(|item| item.can_retain);
             if actor.is_persistent && actor.is_alive() {
+                if actor.is_leader {
+                    actor.controller = Box::new(Ai);
+                }
                 actor.is_leader = false;
                 if level_up {
-                    actor.level += 1;
                     actor.log_action("survived the battle!");
+                    if actor.kills > 0 {
+                        actor.log_action(&format!("had defeated {} foes so far.", actor.kills));
+                    }
+                    let branch = choose_branch(&self.view, self.autopilot, &actor);
+                    actor.level_up(branch.as_deref());
                 }
-                actor.recover_fully();
+                actor.rest();
                 self.player_team.push_front(actor);
+            } else if actor.is_persistent {
+                actor.statuses.clear();
+                self.fallen.push(actor);
             }
         }
     }
@@ -112,8 +258,16 @@ impl State {
         let current_world_idx = self.world_idx;
         while current_world_idx == self.world_idx {
             self.give_turns();
-            self.view.render(&self.world, &self.actors, self.player_idx);
+            self.resolve_effects();
+            self.view.render(
+                &self.world,
+                &self.actors,
+                self.plan.vision(self.actors[self.player_idx].team),
+                self.player_idx,
+            );
             self.actors.append(&mut self.spawn);
+            self.update_summons();
+            self.revive_fallen();
             self.actors.retain(|a| a.is_alive() || !a.is_projectile());
             self.world.clear_debris();
             self.check_exits();
@@ -128,6 +282,86 @@ impl State {
         }
     }
 
+    fn resolve_effects(&mut self) {
+        let mut effects = mem::take(&mut self.world.effects);
+        for effect in effects.iter().filter(|effect| effect.is_harmful()) {
+            for pos in effect.cells(&self.world) {
+                for actor in self
+                    .actors
+                    .iter_mut()
+                    .filter(|a| a.pos == pos && a.is_combatant())
+                {
+                    effect.hurt(actor, &mut self.world);
+                }
+                if effect.kind == EFFECT_EXPLOSION {
+                    self.world.burn(pos);
+                }
+            }
+        }
+        effects.retain_mut(|effect| effect.tick());
+        effects.append(&mut self.world.effects);
+        self.world.effects = effects;
+    }
+
+    // Life gems used during the turn raise fallen teammates where their corpses lay.
+    fn revive_fallen(&mut self) {
+        for (uid, pos) in self.world.revivals.drain(..) {
+            if let Some(actor) = self
+                .actors
+                .iter_mut()
+                .find(|a| a.uid == uid && !a.is_alive())
+            {
+                // a player's character is theirs again, rather than up for grabs:
+                actor.is_leader = actor.controller.is_human();
+                actor.revive(pos);
+            }
+        }
+    }
+
+    // Summons follow their summoner, and vanish when bound to a dead one.
+    fn update_summons(&mut self) {
+        let called_by: HashSet<usize> = self.actors.iter().filter_map(|a| a.summoner).collect();
+        let summoners: Vec<_> = self
+            .actors
+            .iter()
+            .filter(|a| a.is_alive() && called_by.contains(&a.uid))
+            .map(|a| {
+                let quarry = a.nearest_enemy_in_sight(&self.actors, &self.world, &self.plan);
+                (a.uid, a.pos, quarry)
+            })
+            .collect();
+        for actor in self.actors.iter_mut() {
+            actor.summons = 0;
+        }
+        for idx in 0..self.actors.len() {
+            let summoner = match self.actors[idx].summoner {
+                Some(uid) if self.actors[idx].is_alive() => uid,
+                _ => continue,
+            };
+            match summoners
+                .iter()
+                .find(|&&(uid, _pos, _quarry)| uid == summoner)
+            {
+                Some(&(_uid, pos, quarry)) => {
+                    self.actors[idx].leash = Some(pos);
+                    self.actors[idx].quarry = quarry;
+                }
+                None if self.actors[idx].is_bound => {
+                    self.actors[idx].act_vanish(&mut self.world);
+                    continue;
+                }
+                None => {
+                    self.actors[idx].leash = None;
+                    self.actors[idx].quarry = None;
+                    continue;
+                }
+            }
+            if let Some(actor) = self.actors.iter_mut().find(|a| a.uid == summoner) {
+                actor.summons += 1;
+            }
+        }
+    }
+
     fn _is_cleared(&self) -> bool {
         self.plan.num_enemies() <= 5
     }
@@ -144,21 +378,26 @@ impl State {
 
     fn give_turn(&mut self, idx: usize) {
         self.plan.fast_update(&self.actors);
-        let choice = if idx == self.player_idx {
-            // do the expensive update while waiting for the player
-            self.plan.update(&self.team_idxs, &self.world, &self.actors);
-            self.choice_from_player()
+        let choice = if self.actors[idx].is_winding_up() {
+            ACT_WAIT // committed to the skill being prepared
         } else {
-            self.choice_from_ai(idx)
+            match self.choice_from_controller(idx) {
+                Some(choice) => choice,
+                None => {
+                    // do the expensive update while waiting for the player
+                    self.plan.update(&self.team_idxs, &self.world, &self.actors);
+                    self.choice_from_player(idx)
+                }
+            }
         };
         // split actors, excluding current, to prevent reborrowing
-        let (have_acted, yet_to_act) = (&mut self.actors).split_at_mut(idx);
+        let (have_acted, yet_to_act) = self.actors.split_at_mut(idx);
         if let Some((actor, yet_to_act)) = yet_to_act.split_first_mut() {
             actor.time = self.time;
             actor.act(
                 choice,
                 &mut self.world,
-                &self.plan,
+                &mut self.plan,
                 &mut vec![have_acted, yet_to_act],
                 &mut self.spawn,
             );
@@ -166,17 +405,166 @@ impl State {
         }
     }
 
+    fn choice_from_controller(&mut self, idx: usize) -> Option<u8> {
+        let actor = &mut self.actors[idx];
+        let mut controller = mem::replace(&mut actor.controller, Box::new(Ai));
+        let choice = controller.choose(actor, &self.world, &self.plan);
+        actor.controller = controller;
+        choice
+    }
+
+    // Returns false if the player cancelled aiming the selected skill.
+    fn aim_skill(&mut self, idx: usize) -> bool {
+        let skill = match skills_registry::lookup(&self.actors[idx].selected_skill()) {
+            Some(skill) => skill,
+            None => return true,
+        };
+        if skill.targeting() == TARGET_NONE {
+            return true;
+        }
+        self.actors[idx].log_event("[Aim with the movement keys, then press z]", 0);
+        let targeting = (skill.targeting(), skill.reach(&self.actors[idx]));
+        match self
+            .view
+            .choose_target(&self.world, &self.actors, &self.plan, idx, targeting)
+        {
+            Some(target) => {
+                self.actors[idx].target = Some(target);
+                true
+            }
+            None => {
+                self.actors[idx].log_event("[Cancelled]", 0);
+                false
+            }
+        }
+    }
+
+    // Returns false if the player had nothing suitable or changed their mind.
+    fn choose_item(&mut self, idx: usize, action: u8) -> bool {
+        let inventory = &self.actors[idx].inventory;
+        let (prompt, candidates): (&str, Vec<usize>) = if action == ACT_USE {
+            let usable = (0..inventory.len()).filter(|&item_idx| {
+                let item = &inventory[item_idx];
+                item_effects::is_consumable(item.kind)
+                    || item.is_equipment()
+                    || item.kind == ITEM_LIFE_GEM
+            });
+            ("Use which item?", usable.collect())
+        } else if action == ACT_GIVE {
+            ("Give which item?", (0..inventory.len()).collect())
+        } else {
+            ("Throw which item?", (0..inventory.len()).collect())
+        };
+        if candidates.is_empty() {
+            self.actors[idx].log_event("[Nothing to choose from]", 0);
+            return false;
+        }
+        let items: Vec<&Item> = candidates
+            .iter()
+            .map(|&item_idx| &inventory[item_idx])
+            .collect();
+        match self.view.choose_item(prompt, &items) {
+            Some(choice) => {
+                self.actors[idx].chosen_item = Some(candidates[choice]);
+                true
+            }
+            None => {
+                self.actors[idx].log_event("[Cancelled]", 0);
+                false
+            }
+        }
+    }
+
+    // An open container with something in it, that the player is walking into.
+    fn container_ahead(&self, idx: usize, input: u8) -> Option<usize> {
+        if self.autopilot || !ACT_MOVES.contains(&input) {
+            return None;
+        }
+        let pos = self.world.offset(self.actors[idx].pos, input);
+        self.world.container_at(pos).filter(|&chest_idx| {
+            let chest = &self.world.items[chest_idx];
+            chest.kind == ITEM_CHEST_OPEN && !chest.contents.is_empty()
+        })
+    }
+
+    // Picks what to take from the container ahead.
+    fn choose_from_container(&mut self, idx: usize, input: u8) -> bool {
+        let chest = match self.container_ahead(idx, input) {
+            Some(chest_idx) => &self.world.items[chest_idx],
+            None => return false,
+        };
+        let items: Vec<&Item> = chest.contents.iter().collect();
+        let prompt = format!("Take what from {}?", chest.name);
+        match self.view.choose_item(&prompt, &items) {
+            Some(choice) => {
+                self.actors[idx].wanted_item = Some(choice);
+                true
+            }
+            None => {
+                self.actors[idx].log_event("[Cancelled]", 0);
+                false
+            }
+        }
+    }
+
+    // Picks what to take from the teammate alongside, and what to give back.
+    fn choose_trade(&mut self, idx: usize) -> bool {
+        let actor = &self.actors[idx];
+        let ahead = self.world.offset(actor.pos, actor.direction);
+        let partner = (0..self.actors.len())
+            .filter(|&other| actor.is_trade_partner(&self.actors[other]))
+            .min_by_key(|&other| self.actors[other].pos != ahead);
+        let partner = match partner {
+            Some(partner) if !self.actors[partner].inventory.is_empty() => &self.actors[partner],
+            _ => {
+                self.actors[idx].log_event("[Nobody alongside has anything to trade]", 0);
+                return false;
+            }
+        };
+        let theirs: Vec<&Item> = partner.inventory.iter().collect();
+        let prompt = format!("Take what from {}?", partner.name);
+        let wanted = match self.view.choose_item(&prompt, &theirs) {
+            Some(wanted) => wanted,
+            None => {
+                self.actors[idx].log_event("[Cancelled]", 0);
+                return false;
+            }
+        };
+        let mine: Vec<&Item> = actor.inventory.iter().collect();
+        let chosen = if mine.is_empty() {
+            None
+        } else {
+            match self.view.choose_item("Give what in return?", &mine) {
+                Some(chosen) => Some(chosen),
+                None => {
+                    self.actors[idx].log_event("[Cancelled]", 0);
+                    return false;
+                }
+            }
+        };
+        let partner = partner.uid;
+        self.actors[idx].wanted_item = Some(wanted);
+        self.actors[idx].trade_partner = Some(partner);
+        self.actors[idx].chosen_item = chosen;
+        true
+    }
+
     fn choice_from_ai(&mut self, idx: usize) -> u8 {
         self.actors[idx].choose(&self.world, &self.plan)
     }
 
-    fn choice_from_player(&mut self) -> u8 {
-        let player_idx = self.player_idx;
+    fn choice_from_player(&mut self, idx: usize) -> u8 {
+        self.player_idx = idx;
         loop {
-            self.view.render(&self.world, &self.actors, self.player_idx);
+            self.view.render(
+                &self.world,
+                &self.actors,
+                self.plan.vision(self.actors[idx].team),
+                idx,
+            );
             let input = if self.autopilot {
                 self.plan.tactic_attack();
-                self.choice_from_ai(player_idx)
+                self.choice_from_ai(idx)
             } else {
                 self.view.get_key_input()
             };
@@ -184,44 +572,76 @@ impl State {
                 self.view.scroll_log_up(0);
             }
             match input {
-                32 => self.player_mut().next_skill(),
-                41 => self.player_mut().inventory(),
+                ACT_SKILL if !self.autopilot => {
+                    if self.aim_skill(idx) {
+                        return input;
+                    }
+                }
+                _ if self.container_ahead(idx, input).is_some() => {
+                    if self.choose_from_container(idx, input) {
+                        return input;
+                    }
+                }
+                ACT_USE | ACT_THROW | ACT_GIVE if !self.autopilot => {
+                    if self.choose_item(idx, input) {
+                        return input;
+                    }
+                }
+                ACT_TRADE if !self.autopilot => {
+                    if self.choose_trade(idx) {
+                        return input;
+                    }
+                }
+                32 => self.actors[idx].next_skill(),
+                41 if !self.autopilot => {
+                    if let Some((item_idx, action)) = self.view.show_inventory(&self.actors[idx]) {
+                        self.actors[idx].chosen_item = Some(item_idx);
+                        return action;
+                    }
+                }
                 55 => {
-                    let pos = self.player().pos;
+                    let pos = self.actors[idx].pos;
                     self.plan.tactic_defend(pos);
-                    self.player_mut()
-                        .log_action("yelled, 'defend this position!'");
+                    self.actors[idx].log_action("yelled, 'defend this position!'");
                 }
                 56 => {
                     self.plan.tactic_follow();
-                    self.player_mut().log_action("yelled, 'follow me!'");
+                    self.actors[idx].log_action("yelled, 'follow me!'");
                 }
                 57 => {
                     self.plan.tactic_attack();
-                    self.player_mut().log_action("yelled, 'attack!'");
+                    self.actors[idx].log_action("yelled, 'attack!'");
                 }
                 58 => {
                     self.plan.tactic_retreat();
-                    self.player_mut().log_action("yelled, 'retreat!'");
+                    self.actors[idx].log_action("yelled, 'retreat!'");
                 }
                 59 => {
                     self.plan.tactic_attack();
-                    return self.choice_from_ai(player_idx);
+                    return self.choice_from_ai(idx);
                 }
                 60 => {
-                    self.player_control_next();
-                    return self.choice_from_ai(player_idx);
+                    self.player_control_next(idx);
+                    return self.choice_from_ai(idx);
                 }
                 61..=69 => {
-                    self.player_control_set_by_number(usize::from(input - 60));
-                    return self.choice_from_ai(player_idx);
+                    self.player_control_set_by_number(idx, usize::from(input - 60));
+                    return self.choice_from_ai(idx);
                 }
                 70 => self.view.scroll_log_up(1),
                 71 => self.view.scroll_log_down(1),
+                72 => {
+                    let msg = if self.view.toggle_fog_of_war() {
+                        "[Fog of war on]"
+                    } else {
+                        "[Fog of war off]"
+                    };
+                    self.actors[idx].log_event(msg, 0);
+                }
                 90 => {
                     if let Ok(keys) = self.view.reload_keybindings() {
                         for key in keys {
-                            self.player_mut().log_event(key.as_str(), 0);
+                            self.actors[idx].log_event(key.as_str(), 0);
                             self.view.scroll_log_up(1);
                         }
                         self.view.scroll_log_down(2);
@@ -233,6 +653,14 @@ impl State {
     }
 
     fn update_logs(&mut self) {
+        for (uid, txt, is_kill) in self.world.credits.drain(0..) {
+            if let Some(actor) = self.actors.iter_mut().find(|a| a.uid == uid) {
+                actor.log_action(&txt);
+                if is_kill {
+                    actor.kills += 1;
+                }
+            }
+        }
         for (pos, txt, important) in self.world.log.drain(0..) {
             for actor in self.actors.iter_mut().filter(|a| a.is_alive()) {
                 if important {
@@ -248,7 +676,7 @@ impl State {
         if self.player().is_ready_to_act(self.time) && self._is_cleared() {
             if let Some(exit) = self.world.exits.iter().find(|x| x.pos == self.player().pos) {
                 if self.autopilot || self.view.yes_or_no("Exit?") {
-                    return self.world_idx = exit.level as usize;
+                    self.world_idx = exit.level as usize;
                 }
             }
         }
@@ -262,50 +690,115 @@ impl State {
         &mut self.actors[self.player_idx]
     }
 
+    // Gives every player without a living character the first free one.
     fn player_control_confirm(&mut self) {
-        if self.player_idx >= self.actors.len()
-            || !self.player().is_leader
-            || !self.player().is_playable()
-        {
-            self.player_idx = 0;
-            self.player_control_set_by_number(1);
+        for idx in 0..self.actors.len() {
+            let actor = &self.actors[idx];
+            if actor.controller.is_human() && !(actor.is_leader && actor.is_playable()) {
+                self.player_control_release(idx);
+            }
+        }
+        let mut humans = self
+            .actors
+            .iter()
+            .filter(|a| a.controller.is_human())
+            .count();
+        while humans < self.humans {
+            match self.actors.iter().position(_can_take_over) {
+                Some(idx) => self.player_control_take(idx),
+                None => break,
+            }
+            humans += 1;
+        }
+        if humans == 0 {
+            return self.world_idx = 0;
+        }
+        if self.player_idx >= self.actors.len() || !self.player().controller.is_human() {
+            self.player_idx = self
+                .actors
+                .iter()
+                .position(|a| a.controller.is_human())
+                .unwrap();
         }
     }
 
-    fn player_control_set_by_number(&mut self, mut num: usize) {
-        if self.player().is_playable() {
-            self.player_mut().is_leader = false;
+    // Another player's character, or a scripted one, can't be taken over.
+    fn player_control_set_by_number(&mut self, from: usize, num: usize) {
+        let playable: Vec<usize> = (0..self.actors.len())
+            .filter(|&idx| self.actors[idx].is_playable())
+            .collect();
+        if playable.is_empty() {
+            return;
         }
-        num = cmp::min(num, self.actors.iter().filter(|a| a.is_playable()).count());
-        for (idx, actor) in self.actors.iter_mut().enumerate() {
-            if actor.is_playable() {
-                num -= 1;
-                if num == 0 {
-                    self.player_idx = idx;
-                    return actor.is_leader = true;
-                }
-            }
+        let idx = playable[cmp::min(num, playable.len()) - 1];
+        if idx != from && _can_take_over(&self.actors[idx]) {
+            self.player_control_release(from);
+            self.player_control_take(idx);
         }
-        self.world_idx = 0;
     }
 
-    fn player_control_next(&mut self) {
-        if self.player().is_playable() {
-            self.player_mut().is_leader = false;
-        }
-        for _ii in 0..self.actors.len() {
-            self.player_idx = (self.player_idx + 1) % self.actors.len();
-            if self.player().is_playable() {
-                return self.player_mut().is_leader = true;
+    fn player_control_next(&mut self, from: usize) {
+        for step in 1..self.actors.len() {
+            let idx = (from + step) % self.actors.len();
+            if _can_take_over(&self.actors[idx]) {
+                self.player_control_release(from);
+                return self.player_control_take(idx);
             }
         }
     }
+
+    fn player_control_take(&mut self, idx: usize) {
+        self.player_idx = idx;
+        self.actors[idx].controller = Box::new(Human);
+        self.actors[idx].is_leader = true;
+    }
+
+    fn player_control_release(&mut self, idx: usize) {
+        if self.actors[idx].is_playable() {
+            self.actors[idx].is_leader = false;
+        }
+        self.actors[idx].controller = Box::new(Ai);
+    }
+}
+
+// Only characters left to the AI are free for a player to take.
+fn _can_take_over(actor: &Actor) -> bool {
+    actor.is_playable() && actor.controller.is_ai()
+}
+
+// Which of the skills unlocked at the actor's next level it should learn.
+fn choose_branch(view: &View, autopilot: bool, actor: &Actor) -> Option<String> {
+    let unlocks = progression::unlocks(actor.kind, actor.level + 1);
+    if unlocks.len() < 2 {
+        return None;
+    }
+    let skills: Vec<String> = unlocks.into_iter().map(|unlock| unlock.0).collect();
+    if autopilot {
+        return Some(skills[rand_int(skills.len() as u16) as usize].clone());
+    }
+    // a new skill has to be picked, so cancelling settles for the first:
+    let prompt = format!(
+        "{} can learn (any other key picks 1):",
+        actor.name.to_sentence_case()
+    );
+    let choice = view.choose_from(&prompt, &skills).unwrap_or(0);
+    Some(skills[choice].clone())
+}
+
+// The price in item.csv is per level of the fallen.
+fn _resurrection_price(actor: &Actor) -> u32 {
+    u32::from(Item::new(ITEM_RESURRECTION, 1, 0).price) * u32::from(actor.level)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use constants::EFFECT_CLOUD;
+    use controller::Scripted;
+    use effect::Effect;
     use glad_loader;
+    use skills::{can_summon_faerie, summon_faerie};
+    use status::StatusKind;
 
     fn fixtures() -> State {
         let mut state = State::new(
@@ -334,6 +827,71 @@ mod tests {
         assert!(state.player().is_leader);
     }
 
+    #[test]
+    fn test_two_players() {
+        let mut state = fixtures();
+        state.humans = 2;
+        state.player_control_confirm();
+        let humans: Vec<usize> = (0..state.actors.len())
+            .filter(|&idx| state.actors[idx].controller.is_human())
+            .collect();
+        assert_eq!(humans.len(), 2);
+        assert!(humans.iter().all(|&idx| state.actors[idx].is_leader));
+
+        // switching skips the other player's character:
+        state.player_control_next(humans[0]);
+        assert!(state.actors[humans[1]].controller.is_human());
+        assert!(!state.actors[humans[0]].controller.is_human());
+        assert_ne!(state.player_idx, humans[1]);
+
+        // a player whose character falls gets a free one:
+        state.actors[humans[1]].act_die(&mut state.world);
+        state.player_control_confirm();
+        assert_eq!(
+            state
+                .actors
+                .iter()
+                .filter(|a| a.controller.is_human())
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_controllers() {
+        let mut state = fixtures();
+        state.player_control_confirm();
+        let player_idx = state.player_idx;
+        assert!(state.choice_from_controller(player_idx).is_none());
+        state.player_control_next(player_idx);
+        assert!(state.choice_from_controller(player_idx).is_some());
+        state.actors[player_idx].controller = Box::new(Scripted::new(vec![3]));
+        assert_eq!(state.choice_from_controller(player_idx), Some(3));
+    }
+
+    #[test]
+    fn test_switching_skips_scripts() {
+        let mut state = fixtures();
+        state.player_control_confirm();
+        let scripted = (0..state.actors.len())
+            .find(|&idx| idx != state.player_idx && state.actors[idx].is_playable())
+            .unwrap();
+        state.actors[scripted].controller = Box::new(Scripted::new(vec![3, 4]));
+        for _ii in 0..state.actors.len() {
+            let from = state.player_idx;
+            state.player_control_next(from);
+            assert_ne!(state.player_idx, scripted);
+        }
+        let number = (0..=scripted)
+            .filter(|&idx| state.actors[idx].is_playable())
+            .count();
+        let from = state.player_idx;
+        state.player_control_set_by_number(from, number);
+        assert_ne!(state.player_idx, scripted);
+        assert_eq!(state.choice_from_controller(scripted), Some(3));
+        assert_eq!(state.choice_from_controller(scripted), Some(4));
+    }
+
     #[test]
     fn test_loop_turns() {
         let mut state = fixtures();
@@ -343,4 +901,133 @@ mod tests {
         // state.view.hide();
         assert!(state.world_idx == 0); // defeat condition
     }
+
+    #[test]
+    fn test_resolve_effects() {
+        let mut state = fixtures();
+        let pos = state.player().pos;
+        let health = state.player().health;
+        let mut cloud = Effect::new(EFFECT_CLOUD, pos, 1, 1);
+        cloud.turns = 2;
+        state.world.add_effect(cloud);
+        state.resolve_effects();
+        assert_eq!(state.player().health, health);
+
+        // clouds hurt on the turns of those inside:
+        let idx = state.player_idx;
+        state.actors[idx].update(&mut state.world);
+        assert!(state.player().health < health);
+        assert!(state.player().has_status(StatusKind::Poisoned));
+        state.resolve_effects();
+        state.resolve_effects();
+        assert!(state.world.effects.is_empty());
+    }
+
+    #[test]
+    fn test_update_summons() {
+        let mut state = fixtures();
+        let mut druid = Actor::new(13, 3, 0, state.player().pos);
+        let mut spawn = Vec::new();
+        while can_summon_faerie(&druid, &state.world, &state.plan) {
+            summon_faerie(&mut druid, &mut state.world, &state.plan, &mut spawn);
+        }
+        assert_eq!(spawn.len(), 2);
+        let druid_uid = druid.uid;
+        state.add_actor(druid);
+        state.actors.append(&mut spawn);
+        state.update_summons();
+        let druid_idx = state
+            .actors
+            .iter()
+            .position(|a| a.uid == druid_uid)
+            .unwrap();
+        assert_eq!(state.actors[druid_idx].summons, 2);
+        // summons vanish along with whoever called them:
+        state.actors[druid_idx].health = 0;
+        state.update_summons();
+        assert!(!state
+            .actors
+            .iter()
+            .any(|a| a.summoner == Some(druid_uid) && a.is_alive()));
+    }
+
+    #[test]
+    fn test_wallet() {
+        let mut state = fixtures();
+        state.player_control_confirm();
+        state.player_mut().is_persistent = true;
+        state.player_mut().add_to_inventory(Item::new(2, 1, 0));
+        state.player_mut().add_to_inventory(Item::new(3, 1, 0));
+        state.extract_team(false);
+        assert_eq!(state.wallet, 11);
+        assert!(state
+            .player_team
+            .iter()
+            .all(|a| a.inventory.iter().all(|i| !i.is_currency())));
+    }
+
+    #[test]
+    fn test_healing() {
+        let mut state = fixtures();
+        state.player_control_confirm();
+        state.player_mut().is_persistent = true;
+        let max = state.player().max_health();
+        state.player_mut().health = 1;
+        state.extract_team(false);
+        assert_eq!(state.player_team[0].health, 1 + (max - 1) / 2);
+        state.wallet = 10;
+        let healing = Item::new(ITEM_HEALING, 1, 0);
+        state.buy(&healing, 0);
+        assert_eq!(state.wallet, 10 - u32::from(healing.price));
+        assert_eq!(state.player_team[0].health, max);
+        assert!(state.player_team[0].inventory.is_empty());
+    }
+
+    #[test]
+    fn test_resurrection() {
+        let mut state = fixtures();
+        state.player_control_confirm();
+        state.player_mut().is_persistent = true;
+        let uid = state.player().uid;
+        state.actors[state.player_idx].act_die(&mut state.world);
+        state.world.revive(uid, (1, 1));
+        let player_idx = state.player_idx;
+        state.revive_fallen();
+        assert!(state.player().is_alive());
+        assert!(state.world.revivals.is_empty());
+        assert!(state.player().is_leader);
+        state.player_control_confirm();
+        assert_eq!(state.player_idx, player_idx);
+        state.actors[state.player_idx].act_die(&mut state.world);
+        state.extract_team(false);
+        assert_eq!(state.fallen.len(), 1);
+        let price = u32::from(Item::new(ITEM_RESURRECTION, 1, 0).price);
+        assert!(price > 0);
+        assert_eq!(_resurrection_price(&state.fallen[0]), price);
+        // there's no room for the fallen in a full party:
+        let team = state.player_team.len();
+        for _ii in team..TEAM_SIZE {
+            state.player_team.push_back(Actor::new(0, 1, 0, (0, 0)));
+        }
+        assert!(!state.resurrect(0));
+        assert_eq!(state.fallen.len(), 1);
+        state.player_team.truncate(team);
+        assert!(state.resurrect(0));
+        assert!(state.fallen.is_empty());
+        assert!(state.player_team[0].is_alive());
+    }
+
+    #[test]
+    fn test_hand_over() {
+        let mut state = fixtures();
+        let mut giver = Actor::new(0, 1, 0, (1, 1));
+        giver.add_to_inventory(Item::new(6, 1, 0));
+        state.player_team.push_back(giver);
+        state.player_team.push_back(Actor::new(2, 1, 0, (1, 2)));
+        state.hand_over(0, 0, 0);
+        assert_eq!(state.player_team[0].inventory.len(), 1);
+        state.hand_over(0, 0, 1);
+        assert!(state.player_team[0].inventory.is_empty());
+        assert!(state.player_team[1].inventory[0].is_equipped);
+    }
 }
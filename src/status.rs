@@ -0,0 +1,142 @@
+// Handles lasting effects on actors like poison, fire and haste.
+use ncurses::*;
+
+const MAX_POISON_STACKS: u16 = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StatusKind {
+    Poisoned,
+    Burning,
+    Slowed,
+    Hasted,
+    Shielded,
+    Rooted,
+    Confused,
+}
+
+const ALL_KINDS: [StatusKind; 7] = [
+    StatusKind::Poisoned,
+    StatusKind::Burning,
+    StatusKind::Slowed,
+    StatusKind::Hasted,
+    StatusKind::Shielded,
+    StatusKind::Rooted,
+    StatusKind::Confused,
+];
+
+#[derive(Clone)]
+pub struct Status {
+    pub kind: StatusKind,
+    pub turns: u16,
+    pub stacks: u16,
+}
+
+impl Status {
+    pub fn new(kind: StatusKind, turns: u16) -> Status {
+        Status {
+            kind,
+            turns,
+            stacks: 1,
+        }
+    }
+
+    /// Poison stacks up; anything else just lasts as long as the longer dose.
+    pub fn reapply(&mut self, turns: u16) {
+        if self.kind == StatusKind::Poisoned {
+            self.stacks = (self.stacks + 1).min(MAX_POISON_STACKS);
+        }
+        self.turns = self.turns.max(turns);
+    }
+
+    /// Counts down one turn, returning whether the status is still active.
+    pub fn tick(&mut self) -> bool {
+        self.turns -= self.turns.min(1);
+        self.turns > 0
+    }
+
+    /// The status that this one wears off when applied (e.g. haste cures slow).
+    pub fn cancels(&self) -> Option<StatusKind> {
+        match self.kind {
+            StatusKind::Slowed => Some(StatusKind::Hasted),
+            StatusKind::Hasted => Some(StatusKind::Slowed),
+            _ => None,
+        }
+    }
+
+    /// Whether the status hurts or hinders whoever has it.
+    pub fn is_harmful(&self) -> bool {
+        !(self.kind == StatusKind::Hasted || self.kind == StatusKind::Shielded)
+    }
+
+    pub fn name(&self) -> &str {
+        match self.kind {
+            StatusKind::Poisoned => "poisoned",
+            StatusKind::Burning => "burning",
+            StatusKind::Slowed => "slowed",
+            StatusKind::Hasted => "hasted",
+            StatusKind::Shielded => "shielded",
+            StatusKind::Rooted => "rooted",
+            StatusKind::Confused => "confused",
+        }
+    }
+
+    pub fn letter(&self) -> char {
+        match self.kind {
+            StatusKind::Poisoned => 'P',
+            StatusKind::Burning => 'B',
+            StatusKind::Slowed => 'S',
+            StatusKind::Hasted => 'H',
+            StatusKind::Shielded => 'O',
+            StatusKind::Rooted => 'R',
+            StatusKind::Confused => 'C',
+        }
+    }
+
+    /// The color pair the status is drawn in, on the map and in the roster alike.
+    pub fn color(&self) -> i16 {
+        100 + match self.kind {
+            StatusKind::Poisoned => COLOR_GREEN,
+            StatusKind::Burning => COLOR_RED,
+            StatusKind::Slowed | StatusKind::Rooted => COLOR_BLUE,
+            StatusKind::Hasted => COLOR_YELLOW,
+            StatusKind::Shielded => COLOR_WHITE,
+            StatusKind::Confused => COLOR_MAGENTA,
+        }
+    }
+}
+
+/// Looks up a status kind by the name it is shown with (e.g. "poisoned").
+pub fn kind_named(name: &str) -> Option<StatusKind> {
+    ALL_KINDS
+        .iter()
+        .cloned()
+        .find(|&kind| Status::new(kind, 0).name() == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reapply_and_tick() {
+        let mut poison = Status::new(StatusKind::Poisoned, 2);
+        for _ii in 0..10 {
+            poison.reapply(1);
+        }
+        assert_eq!(poison.stacks, MAX_POISON_STACKS);
+        assert_eq!(poison.turns, 2);
+        let mut fire = Status::new(StatusKind::Burning, 2);
+        fire.reapply(3);
+        assert_eq!(fire.stacks, 1);
+        assert!(fire.tick() && fire.tick() && !fire.tick());
+        assert_eq!(
+            Status::new(StatusKind::Hasted, 1).cancels(),
+            Some(StatusKind::Slowed)
+        );
+        assert_eq!(Status::new(StatusKind::Shielded, 1).letter(), 'O');
+        assert_eq!(kind_named("rooted"), Some(StatusKind::Rooted));
+        assert_eq!(kind_named("afflicted"), None);
+        assert!(Status::new(StatusKind::Burning, 1).is_harmful());
+        assert!(!Status::new(StatusKind::Hasted, 1).is_harmful());
+    }
+}
@@ -0,0 +1,65 @@
+// Handles how long summoned actors last and how many can be called at once.
+use csv;
+use std::cell::RefCell;
+use std::error::Error;
+
+type SummonStats = (
+    String, // skill
+    u16,    // lifetime in turns (0 lasts forever)
+    u16,    // most summons alive per summoner
+    u8,     // whether summons vanish with their summoner
+);
+
+thread_local!(static _SUMMON_CSV_CACHE: RefCell<Vec<SummonStats>> = const { RefCell::new(Vec::new()) });
+
+pub fn lifetime(skill: &str) -> Option<u16> {
+    match _stats(skill).1 {
+        0 => None,
+        turns => Some(turns),
+    }
+}
+
+pub fn limit(skill: &str) -> u16 {
+    _stats(skill).2
+}
+
+pub fn is_bound(skill: &str) -> bool {
+    _stats(skill).3 != 0
+}
+
+fn _stats(skill: &str) -> SummonStats {
+    _load_from_csv("config/glad/summon.csv")
+        .unwrap()
+        .into_iter()
+        .find(|row| row.0 == skill)
+        .unwrap_or_else(|| panic!("{} has no summon stats", skill))
+}
+
+fn _load_from_csv(config: &str) -> Result<Vec<SummonStats>, Box<dyn Error>> {
+    _SUMMON_CSV_CACHE.with(|summon_cache_cell| {
+        let mut summon_cache = summon_cache_cell.borrow_mut();
+        if summon_cache.is_empty() {
+            for record in csv::Reader::from_path(config)?.deserialize() {
+                summon_cache.push(record?);
+            }
+        }
+        Ok(summon_cache.clone())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use skills_registry;
+
+    #[test]
+    fn test_summon_stats() {
+        let names = skills_registry::names();
+        for row in _load_from_csv("config/glad/summon.csv").unwrap() {
+            assert!(names.contains(&row.0), "{} is not a skill", row.0);
+            assert!(row.2 > 0, "{} can never be used", row.0);
+        }
+        assert!(lifetime("summon_faerie").is_some() && is_bound("summon_faerie"));
+        assert!(lifetime("multiply").is_none() && !is_bound("multiply"));
+    }
+}
@@ -1,11 +1,15 @@
 // Handles interactivity: reading keys, writing to the screen.
 use actor::Actor;
+use constants::{ACT_CANCEL, ACT_DROP, ACT_GIVE, ACT_SKILL, ACT_USE, ACT_WAIT, TARGET_ALLY};
 use csv;
-use item::Item;
+use inflector::Inflector;
+use item::{self, Item};
 use ncurses::*;
+use plan::Plan;
 use std::cmp;
 use std::collections::HashMap;
 use std::error::Error;
+use vision::Vision;
 use world::World;
 
 pub struct View {
@@ -16,6 +20,13 @@ pub struct View {
     last_key_pressed: i32,
     animation_frame: i32, // delay per animation frame (dynamic)
     animation_cycle: i32, // total delay between player turns
+    fog_of_war: bool,
+}
+
+impl Default for View {
+    fn default() -> View {
+        View::new()
+    }
 }
 
 impl View {
@@ -28,6 +39,7 @@ impl View {
             last_key_pressed: ERR,
             animation_frame: 0,
             animation_cycle: 200,
+            fog_of_war: true,
         };
         view.reload_keybindings().unwrap();
         view
@@ -105,19 +117,90 @@ impl View {
         }
     }
 
-    pub fn render(&mut self, world: &World, actors: &[Actor], player: usize) {
+    /// Let the player move a cursor to aim a skill; None if they cancel.
+    pub fn choose_target(
+        &mut self,
+        world: &World,
+        actors: &[Actor],
+        plan: &Plan,
+        player: usize,
+        targeting: (u8, u16),
+    ) -> Option<(u16, u16)> {
+        let (kind, reach) = targeting;
+        let vision = plan.vision(actors[player].team);
+        let origin = actors[player].pos;
+        let mut cursor = world.offset(origin, actors[player].direction);
+        loop {
+            self.render(world, actors, vision, player);
+            let (min_x, min_y, _max_x, _max_y) = self.rect_around(origin, world);
+            let valid = self.render_line_of_fire(world, origin, cursor, reach, (min_x, min_y));
+            mv(i32::from(cursor.1) - min_y, i32::from(cursor.0) - min_x);
+            refresh();
+            match self.get_key_input() {
+                ACT_CANCEL => return None,
+                ACT_WAIT | ACT_SKILL if valid && cursor != origin => {
+                    let is_ally = actors.iter().any(|a| {
+                        a.pos == cursor
+                            && plan.is_allied(a.team, actors[player].team)
+                            && a.is_combatant()
+                    });
+                    if kind != TARGET_ALLY || is_ally {
+                        return Some(cursor);
+                    }
+                }
+                dir if dir < ACT_WAIT => cursor = world.offset(cursor, dir),
+                _ => {}
+            }
+        }
+    }
+
+    /// Highlight the cells between the actor and the cursor, returning whether
+    /// the cursor is within reach and in sight.
+    fn render_line_of_fire(
+        &self,
+        world: &World,
+        origin: (u16, u16),
+        cursor: (u16, u16),
+        reach: u16,
+        corner: (i32, i32),
+    ) -> bool {
+        let mut valid = true;
+        for (dist, pos) in world.line(origin, cursor).into_iter().enumerate() {
+            valid =
+                valid && (reach == 0 || dist < usize::from(reach)) && world.is_in_view(origin, pos);
+            let color = if valid { COLOR_GREEN } else { COLOR_RED };
+            let (y, x) = (i32::from(pos.1) - corner.1, i32::from(pos.0) - corner.0);
+            self.highlight((y, x), color);
+        }
+        valid
+    }
+
+    /// Redraw whatever is on screen at the position against a colored background.
+    fn highlight(&self, yx: (i32, i32), color: i16) {
+        let character = mvinch(yx.0, yx.1) & A_CHARTEXT();
+        attron(COLOR_PAIR(color + 100));
+        mvaddch(yx.0, yx.1, character);
+        attroff(COLOR_PAIR(color + 100));
+    }
+
+    pub fn toggle_fog_of_war(&mut self) -> bool {
+        self.fog_of_war = !self.fog_of_war;
+        self.fog_of_war
+    }
+
+    pub fn render(&mut self, world: &World, actors: &[Actor], vision: &Vision, player: usize) {
         if self.hidden {
             return;
         }
-        self.animation_frame = self.animation_cycle / i32::from(actors[player].move_lag);
+        self.animation_frame = self.animation_cycle / i32::from(actors[player].move_lag());
         self.reset(
             actors.iter().filter(|a| a.is_playable()).count(),
             actors[player].log.len(),
         );
         let focus = actors[player].pos;
         let (min_x, min_y, max_x, max_y) = self.rect_around(focus, world);
-        self.render_world(world, actors, (min_x, min_y, max_x, max_y));
-        let xx = (max_x - min_x) as i32;
+        self.render_world(world, actors, vision, (min_x, min_y, max_x, max_y));
+        let xx = max_x - min_x;
         let yy = self.render_roster(actors, xx);
         self.render_log(&actors[player].log, actors[player].time, (xx + 1, yy));
         mv(i32::from(focus.1) - min_y, i32::from(focus.0) - min_x);
@@ -129,12 +212,32 @@ impl View {
         }
     }
 
-    fn render_world(&self, world: &World, actors: &[Actor], rect: (i32, i32, i32, i32)) {
+    fn render_world(
+        &self,
+        world: &World,
+        actors: &[Actor],
+        vision: &Vision,
+        rect: (i32, i32, i32, i32),
+    ) {
         let (min_x, min_y, max_x, max_y) = rect;
+        let mut effect_colors = HashMap::new();
+        for effect in &world.effects {
+            for pos in effect.cells(world) {
+                effect_colors.insert(pos, effect.color());
+            }
+        }
         for y in min_y..max_y {
             for x in min_x..max_x {
                 mv(y - min_y, x - min_x);
-                self.render_cell((x as u16, y as u16), actors, world);
+                let pos = (x as u16, y as u16);
+                if self.fog_of_war && !vision.is_visible(pos) {
+                    self.render_remembered_cell(pos, vision, world);
+                } else {
+                    self.render_cell(pos, actors, world);
+                    if let Some(&color) = effect_colors.get(&pos) {
+                        self.highlight((y - min_y, x - min_x), color);
+                    }
+                }
             }
         }
     }
@@ -152,6 +255,218 @@ impl View {
         }
     }
 
+    /// Ask the player to pick one of the options by number; None if they press anything else.
+    pub fn choose_from(&self, prompt: &str, options: &[String]) -> Option<usize> {
+        mv(0, 0);
+        clrtoeol();
+        addstr(prompt);
+        for (idx, option) in options.iter().enumerate() {
+            addstr(&format!(" {}) {}", idx + 1, option));
+        }
+        let digit = char::from(getch() as u8).to_digit(10)? as usize;
+        if digit >= 1 && digit <= options.len() {
+            return Some(digit - 1);
+        }
+        None
+    }
+
+    /// Ask the player to pick an item by letter; None if they press anything else.
+    pub fn choose_item(&self, prompt: &str, items: &[&Item]) -> Option<usize> {
+        mv(0, 0);
+        clrtoeol();
+        addstr(prompt);
+        for (idx, item) in items.iter().take(26).enumerate() {
+            addstr(&format!(" {}) {}", char::from(b'a' + idx as u8), item.name));
+        }
+        let key = char::from(getch() as u8);
+        (0..cmp::min(items.len(), 26)).find(|&idx| char::from(b'a' + idx as u8) == key)
+    }
+
+    /// Show the actor's items, and return one with what to do with it.
+    pub fn show_inventory(&self, actor: &Actor) -> Option<(usize, u8)> {
+        let choice = loop {
+            self.render_inventory(actor);
+            let key = char::from(getch() as u8);
+            let idx = match (0..cmp::min(actor.inventory.len(), 26))
+                .find(|&idx| char::from(b'a' + idx as u8) == key)
+            {
+                Some(idx) => idx,
+                None => break None,
+            };
+            let item = &actor.inventory[idx];
+            let use_verb = if item.is_equipped {
+                "take off"
+            } else if item.is_equipment() {
+                "wear"
+            } else {
+                "use"
+            };
+            mv(actor.inventory.len() as i32 + 3, 0);
+            clrtobot();
+            addstr(&format!("{}: d) drop  u) {}  g) give", item.name, use_verb));
+            let action = match char::from(getch() as u8) {
+                'd' => ACT_DROP,
+                'u' => ACT_USE,
+                'g' => ACT_GIVE,
+                _ => continue,
+            };
+            if self.confirm_preview(&actor.stat_preview(idx, action)) {
+                break Some((idx, action));
+            }
+        };
+        clear();
+        choice
+    }
+
+    /// Show what's for sale, returning the ware picked (if any).
+    pub fn show_shop(&self, wallet: u32, wares: &[Item]) -> Option<usize> {
+        clear();
+        mv(0, 0);
+        addstr(&format!(
+            "The shop -- your party has {}:",
+            item::format_price(wallet)
+        ));
+        for (idx, ware) in wares.iter().take(26).enumerate() {
+            mv(idx as i32 + 2, 2);
+            if u32::from(ware.price) > wallet {
+                attron(A_DIM());
+            }
+            addstr(&format!("{}) ", char::from(b'a' + idx as u8)));
+            self.render_item_or_exit(ware);
+            addstr(&format!(" {:<24}", ware.name));
+            addstr(&format!(
+                "{:>7}  ",
+                item::format_price(u32::from(ware.price))
+            ));
+            addstr(&ware.describe());
+            if u32::from(ware.price) > wallet {
+                attroff(A_DIM());
+            }
+        }
+        mv(cmp::min(wares.len(), 26) as i32 + 3, 0);
+        addstr("Buy an item by letter, or press any other key to move on.");
+        refresh();
+        let key = char::from(getch() as u8);
+        let choice =
+            (0..cmp::min(wares.len(), 26)).find(|&idx| char::from(b'a' + idx as u8) == key);
+        clear();
+        choice
+    }
+
+    /// Lists the fallen with what it costs to revive each, and returns one to revive.
+    pub fn show_temple(&self, wallet: u32, offers: &[(&str, u32)]) -> Option<usize> {
+        clear();
+        mv(0, 0);
+        addstr(&format!(
+            "The temple -- your party has {}:",
+            item::format_price(wallet)
+        ));
+        for (idx, &(name, price)) in offers.iter().take(26).enumerate() {
+            mv(idx as i32 + 2, 2);
+            if price > wallet {
+                attron(A_DIM());
+            }
+            addstr(&format!(
+                "{}) revive {:<24}",
+                char::from(b'a' + idx as u8),
+                name
+            ));
+            addstr(&format!("{:>7}", item::format_price(price)));
+            if price > wallet {
+                attroff(A_DIM());
+            }
+        }
+        mv(cmp::min(offers.len(), 26) as i32 + 3, 0);
+        addstr("Revive a teammate by letter, or press any other key to move on.");
+        refresh();
+        let key = char::from(getch() as u8);
+        let choice =
+            (0..cmp::min(offers.len(), 26)).find(|&idx| char::from(b'a' + idx as u8) == key);
+        clear();
+        choice
+    }
+
+    /// Lists the party's equipment with who carries it, and returns one to pass on.
+    pub fn show_stash(&self, stash: &[(&str, &Item)]) -> Option<usize> {
+        clear();
+        mv(0, 0);
+        addstr("The party stash:");
+        for (idx, &(owner, item)) in stash.iter().take(26).enumerate() {
+            mv(idx as i32 + 2, 2);
+            addstr(&format!("{}) ", char::from(b'a' + idx as u8)));
+            self.render_item_or_exit(item);
+            addstr(&format!(" {:<24}", item.name));
+            addstr(&format!("{:<16}", owner));
+            if item.is_equipped {
+                addstr("(worn) ");
+            }
+            addstr(&item.describe());
+        }
+        mv(cmp::min(stash.len(), 26) as i32 + 3, 0);
+        addstr("Pass an item on by letter, or press any other key to move on.");
+        refresh();
+        let key = char::from(getch() as u8);
+        let choice =
+            (0..cmp::min(stash.len(), 26)).find(|&idx| char::from(b'a' + idx as u8) == key);
+        clear();
+        choice
+    }
+
+    fn render_inventory(&self, actor: &Actor) {
+        clear();
+        mv(0, 0);
+        addstr(&format!(
+            "{}'s inventory (carrying {} of {}):",
+            actor.name.to_sentence_case(),
+            actor.load(),
+            actor.capacity()
+        ));
+        if actor.inventory.is_empty() {
+            mv(2, 2);
+            addstr("(nothing)");
+        }
+        for (idx, item) in actor.inventory.iter().take(26).enumerate() {
+            mv(idx as i32 + 2, 2);
+            addstr(&format!("{}) ", char::from(b'a' + idx as u8)));
+            self.render_item_or_exit(item);
+            addstr(&format!(" {:<24}", item.name));
+            if item.is_equipped {
+                attron(COLOR_PAIR(COLOR_YELLOW));
+                addstr("(worn) ");
+                attroff(COLOR_PAIR(COLOR_YELLOW));
+            }
+            attron(A_DIM());
+            addstr(&item.describe());
+            attroff(A_DIM());
+        }
+        mv(actor.inventory.len() as i32 + 3, 0);
+        addstr("Pick an item by letter, or press any other key to close.");
+        refresh();
+    }
+
+    // Shows how stats would change and asks to go ahead.
+    fn confirm_preview(&self, preview: &[(&str, u16, u16)]) -> bool {
+        if preview.is_empty() {
+            return true;
+        }
+        let row = self.screen_xy.1 - preview.len() as i32 - 2;
+        for (idx, &(stat, now, after)) in preview.iter().enumerate() {
+            mv(row + idx as i32, 2);
+            clrtoeol();
+            let is_better = if stat == "move lag" {
+                after < now
+            } else {
+                after > now
+            };
+            let color = if is_better { COLOR_GREEN } else { COLOR_RED };
+            addstr(&format!("{:<10} {:>3} -> ", stat, now));
+            attron(COLOR_PAIR(color));
+            addstr(&format!("{}", after));
+            attroff(COLOR_PAIR(color));
+        }
+        self.yes_or_no("Go ahead?")
+    }
+
     /// Draw actors on top of items on top of exits on top of corpses.
     fn render_cell(&self, pos: (u16, u16), actors: &[Actor], world: &World) {
         assert!(!world.is_out_of_bounds((pos.0 as i16, pos.1 as i16)));
@@ -170,6 +485,24 @@ impl View {
         self.render_floor(world, pos)
     }
 
+    /// Draw explored cells dimmed, with the items last seen there.
+    fn render_remembered_cell(&self, pos: (u16, u16), vision: &Vision, world: &World) {
+        if !vision.is_explored(pos) {
+            addch(' ' as chtype);
+            return;
+        }
+        attron(A_DIM());
+        match vision.remembered_item(pos) {
+            Some(&(glyph, color)) => {
+                attron(COLOR_PAIR(color));
+                addch(glyph as chtype);
+                attroff(COLOR_PAIR(color));
+            }
+            None => self.render_floor(world, pos),
+        }
+        attroff(A_DIM());
+    }
+
     fn render_actor(&self, actor: &Actor) {
         let color = if self.animation_frame != 0 {
             self.actor_status_color(actor)
@@ -206,6 +539,8 @@ impl View {
     fn actor_status_color(&self, actor: &Actor) -> i16 {
         if actor.is_alive() && actor.is_hurt() && actor.is_flesh() {
             return 100 + COLOR_RED;
+        } else if let Some(status) = actor.statuses.first() {
+            return status.color();
         } else if actor.is_leader && actor.team != 0 {
             return 100 + COLOR_CYAN;
         }
@@ -246,11 +581,36 @@ impl View {
                 attroff(COLOR_PAIR(COLOR_RED + 100));
             }
             addstr(&format!("{:>3}/{:<3} ", actor.mana, actor.max_mana()));
+            for status in &actor.statuses {
+                attron(COLOR_PAIR(status.color()));
+                addch(status.letter() as chtype);
+                attroff(COLOR_PAIR(status.color()));
+            }
+            self.render_skill_timers(actor);
             idx += 1;
         }
         mv(idx, col);
         clrtoeol();
-        idx + 1 as i32
+        idx + 1
+    }
+
+    /// Show the skill being wound up, or else any skills cooling down.
+    fn render_skill_timers(&self, actor: &Actor) {
+        if let Some((ref skill, turns)) = actor.winding_up {
+            attron(COLOR_PAIR(COLOR_YELLOW));
+            addstr(&format!(" {}({})...", skill, turns));
+            attroff(COLOR_PAIR(COLOR_YELLOW));
+            return;
+        }
+        attron(A_DIM());
+        for skill in actor
+            .skills
+            .iter()
+            .filter(|skill| actor.cooldown(skill) > 0)
+        {
+            addstr(&format!(" {}:{}", skill, actor.cooldown(skill)));
+        }
+        attroff(A_DIM());
     }
 
     pub fn scroll_log_up(&mut self, amt: usize) {
@@ -0,0 +1,92 @@
+// Handles what a team can currently see and what it remembers having seen.
+use actor::Actor;
+use std::collections::HashMap;
+use world::World;
+
+pub struct Vision {
+    size: (u16, u16),
+    visible: Vec<bool>,
+    explored: Vec<bool>,
+    remembered: HashMap<(u16, u16), (char, i16)>,
+}
+
+impl Vision {
+    pub fn new(size: (u16, u16)) -> Vision {
+        let area = (size.0 * size.1) as usize;
+        Vision {
+            size,
+            visible: vec![false; area],
+            explored: vec![false; area],
+            remembered: HashMap::new(),
+        }
+    }
+
+    /// Recomputes what the team's lookouts see; `opaque` comes from `World::opaque_cells`.
+    pub fn update(&mut self, team: usize, world: &World, actors: &[Actor], opaque: &[bool]) {
+        for cell in self.visible.iter_mut() {
+            *cell = false;
+        }
+        for lookout in actors.iter().filter(|a| a.team == team && a.is_combatant()) {
+            for pos in lookout.field_of_view(world, opaque) {
+                let idx = self._index(pos);
+                self.visible[idx] = true;
+                self.explored[idx] = true;
+                self.remembered.remove(&pos);
+            }
+        }
+        for item in world.exits.iter().chain(world.items.iter()) {
+            if self.is_visible(item.pos) {
+                self.remembered.insert(item.pos, (item.glyph, item.color));
+            }
+        }
+    }
+
+    pub fn is_visible(&self, pos: (u16, u16)) -> bool {
+        self.visible[self._index(pos)]
+    }
+
+    pub fn is_explored(&self, pos: (u16, u16)) -> bool {
+        self.explored[self._index(pos)]
+    }
+
+    pub fn remembered_item(&self, pos: (u16, u16)) -> Option<&(char, i16)> {
+        self.remembered.get(&pos)
+    }
+
+    fn _index(&self, pos: (u16, u16)) -> usize {
+        (pos.1 * self.size.0 + pos.0) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use item::Item;
+
+    #[test]
+    fn test_update() {
+        let mut world = World::new("glad");
+        world.reshape((5, 5));
+        world.add_item(Item::new(1, 1, 0), (4, 4));
+        for yy in 0..5 {
+            world.tiles[yy * 5 + 2] = 0; // a wall down the middle
+        }
+        let mut actors = vec![Actor::new(0, 1, 0, (4, 4))];
+        let mut vision = Vision::new(world.size);
+        vision.update(0, &world, &actors, &world.opaque_cells());
+        assert!(vision.is_visible((2, 2)) && !vision.is_explored((0, 0)));
+        assert!(vision.remembered_item((4, 4)).is_some());
+
+        // the item is remembered after it goes out of sight:
+        actors[0].pos = (0, 0);
+        vision.update(0, &world, &actors, &world.opaque_cells());
+        assert!(!vision.is_visible((4, 4)) && vision.is_explored((4, 4)));
+        assert!(vision.remembered_item((4, 4)).is_some());
+
+        // other teams see through their own lookouts only:
+        actors.push(Actor::new(0, 1, 1, (4, 0)));
+        let mut rivals = Vision::new(world.size);
+        rivals.update(1, &world, &actors, &world.opaque_cells());
+        assert!(rivals.is_visible((4, 4)) && !rivals.is_explored((0, 0)));
+    }
+}
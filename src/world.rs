@@ -1,18 +1,25 @@
 // Handles the scenario's map and the items scattered around it.
-use constants::{ITEM_DOOR, ITEM_TREE};
+use constants::{ITEM_CHEST, ITEM_CHEST_LOCKED, ITEM_CHEST_OPEN};
+use constants::{ITEM_DEBRIS, ITEM_DOOR, ITEM_TREE, TILE_SCORCHED, TILE_TREE};
 use csv;
+use effect::Effect;
 use item::Item;
 use item_effects::{use_as_portal, use_on_item};
 use std::collections::HashMap;
 use std::error::Error;
 use std::str;
 
+const SIGHT_BLOCKED_BY: &str = "#%";
+
 pub struct World {
     pub size: (u16, u16), // cols x rows
     pub items: Vec<Item>,
     pub exits: Vec<Item>,
     pub tiles: Vec<u16>,
     pub log: Vec<((u16, u16), String, bool)>,
+    pub effects: Vec<Effect>,
+    pub credits: Vec<(usize, String, bool)>, // uid, what they did, whether it was a kill
+    pub revivals: Vec<(usize, (u16, u16))>,  // uid of the fallen, and where they rise
     config: String,
     tileset: HashMap<u16, (char, i16)>,
 }
@@ -26,6 +33,9 @@ impl World {
             exits: Vec::new(),
             tiles: Vec::new(),
             log: Vec::new(),
+            effects: Vec::new(),
+            credits: Vec::new(),
+            revivals: Vec::new(),
             tileset: HashMap::new(),
         };
         world._load_tileset().unwrap();
@@ -91,10 +101,40 @@ impl World {
         Some(Item::new(18, 1, 0))
     }
 
+    pub fn container_at(&self, pos: (u16, u16)) -> Option<usize> {
+        self.items
+            .iter()
+            .position(|item| item.pos == pos && item.is_container())
+    }
+
+    /// Opens a container, unlocking it with a key if need be.
+    pub fn open_container(&mut self, idx: usize, tools: &[Item]) -> bool {
+        let chest = &mut self.items[idx];
+        if chest.kind == ITEM_CHEST_LOCKED
+            && !tools.iter().any(|tool| use_on_item(chest, tool.kind))
+        {
+            return false;
+        }
+        if chest.kind == ITEM_CHEST {
+            chest.initialize_as(ITEM_CHEST_OPEN);
+        }
+        true
+    }
+
     pub fn clear_debris(&mut self) {
         self.items.retain(|item| !item.is_debris());
     }
 
+    /// Tells the actor with this uid what their projectile did.
+    pub fn credit(&mut self, uid: usize, txt: &str, is_kill: bool) {
+        self.credits.push((uid, txt.to_owned(), is_kill));
+    }
+
+    /// Asks for the fallen actor with this uid to be brought back.
+    pub fn revive(&mut self, uid: usize, pos: (u16, u16)) {
+        self.revivals.push((uid, pos));
+    }
+
     pub fn log_global(&mut self, txt: &str, pos: (u16, u16), important: bool) {
         self.log.push((pos, txt.to_owned(), important));
     }
@@ -104,13 +144,13 @@ impl World {
         match dir {
             // handle n/s components
             0 | 1 | 7 => to.1 -= 1,
-            3 | 4 | 5 => to.1 += 1,
+            3..=5 => to.1 += 1,
             _ => {}
         }
         match dir {
             // handle e/w components
-            1 | 2 | 3 => to.0 += 1,
-            5 | 6 | 7 => to.0 -= 1,
+            1..=3 => to.0 += 1,
+            5..=7 => to.0 -= 1,
             _ => {}
         }
         if self.is_out_of_bounds(to) {
@@ -119,6 +159,81 @@ impl World {
         (to.0 as u16, to.1 as u16)
     }
 
+    /// Cells on a straight line between the two positions, excluding `from`.
+    pub fn line(&self, from: (u16, u16), to: (u16, u16)) -> Vec<(u16, u16)> {
+        let (dx, dy) = (
+            (i32::from(to.0) - i32::from(from.0)).abs(),
+            -(i32::from(to.1) - i32::from(from.1)).abs(),
+        );
+        let step = (
+            if from.0 < to.0 { 1 } else { -1 },
+            if from.1 < to.1 { 1 } else { -1 },
+        );
+        let (mut pos, mut err) = ((i32::from(from.0), i32::from(from.1)), dx + dy);
+        let mut cells = Vec::new();
+        while pos != (i32::from(to.0), i32::from(to.1)) {
+            let double_err = 2 * err;
+            if double_err >= dy {
+                err += dy;
+                pos.0 += step.0;
+            }
+            if double_err <= dx {
+                err += dx;
+                pos.1 += step.1;
+            }
+            cells.push((pos.0 as u16, pos.1 as u16));
+        }
+        cells
+    }
+
+    /// The first step along the line towards the position, if it isn't here.
+    pub fn direction_to(&self, from: (u16, u16), to: (u16, u16)) -> Option<u8> {
+        let step = *self.line(from, to).first()?;
+        let delta = (
+            i32::from(step.0) - i32::from(from.0),
+            i32::from(step.1) - i32::from(from.1),
+        );
+        (0..8).find(|&dir| {
+            let pos = self.offset(from, dir);
+            (
+                i32::from(pos.0) - i32::from(from.0),
+                i32::from(pos.1) - i32::from(from.1),
+            ) == delta
+        })
+    }
+
+    pub fn is_in_view(&self, from: (u16, u16), to: (u16, u16)) -> bool {
+        let line = self.line(from, to);
+        line.iter()
+            .take(line.len().saturating_sub(1))
+            .all(|&pos| !self.is_opaque(pos))
+    }
+
+    fn is_opaque(&self, pos: (u16, u16)) -> bool {
+        SIGHT_BLOCKED_BY.contains(self.glyph_at(pos))
+            || self
+                .items
+                .iter()
+                .any(|item| item.pos == pos && _blocks_sight(item))
+    }
+
+    /// Which cells block sight, indexed like the tiles, for many lookups at once.
+    pub fn opaque_cells(&self) -> Vec<bool> {
+        let mut opaque: Vec<bool> = self
+            .tiles
+            .iter()
+            .map(|tile| {
+                self.tileset
+                    .get(tile)
+                    .is_some_and(|t| SIGHT_BLOCKED_BY.contains(t.0))
+            })
+            .collect();
+        for item in self.items.iter().filter(|item| _blocks_sight(item)) {
+            opaque[(item.pos.1 * self.size.0 + item.pos.0) as usize] = true;
+        }
+        opaque
+    }
+
     pub fn is_out_of_bounds(&self, pos: (i16, i16)) -> bool {
         pos.0 < 0 || pos.1 < 0 || pos.0 >= self.size.0 as i16 || pos.1 >= self.size.1 as i16
     }
@@ -127,6 +242,18 @@ impl World {
         self.tile_at(pos).0
     }
 
+    pub fn tile_type_at(&self, pos: (u16, u16)) -> Option<u16> {
+        self.tiles
+            .get((pos.1 * self.size.0 + pos.0) as usize)
+            .cloned()
+    }
+
+    pub fn set_tile(&mut self, pos: (u16, u16), tile: u16) {
+        if let Some(old_tile) = self.tiles.get_mut((pos.1 * self.size.0 + pos.0) as usize) {
+            *old_tile = tile;
+        }
+    }
+
     pub fn tile_at(&self, pos: (u16, u16)) -> (char, i16) {
         if let Some(tile) = self.tiles.get((pos.1 * self.size.0 + pos.0) as usize) {
             if self.tileset.contains_key(tile) {
@@ -150,6 +277,22 @@ impl World {
         Ok(())
     }
 
+    /// Fire scorches young trees and reduces grown ones to debris.
+    pub fn burn(&mut self, pos: (u16, u16)) {
+        if self.tile_type_at(pos) == Some(TILE_TREE) {
+            self.set_tile(pos, TILE_SCORCHED);
+        }
+        for item in self.items.iter_mut().filter(|item| item.pos == pos) {
+            if item.kind == ITEM_TREE {
+                item.initialize_as(ITEM_DEBRIS);
+            }
+        }
+    }
+
+    pub fn add_effect(&mut self, effect: Effect) {
+        self.effects.push(effect);
+    }
+
     pub fn add_item(&mut self, mut new_item: Item, pos: (u16, u16)) {
         // prevent multiple placement of doors, trees:
         new_item.pos = pos;
@@ -166,6 +309,10 @@ impl World {
     }
 }
 
+fn _blocks_sight(item: &Item) -> bool {
+    item.kind == ITEM_DOOR || item.kind == ITEM_TREE
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -177,7 +324,7 @@ mod tests {
         world.add_item(Item::new(ITEM_DOOR, 0, 0), (1, 1));
         world.add_item(Item::new(ITEM_KEY, 0, 0), (4, 4));
         let impassable_tiles = String::from("#");
-        return (world, impassable_tiles);
+        (world, impassable_tiles)
     }
 
     #[test]
@@ -205,6 +352,29 @@ mod tests {
         assert_eq!(world.neighbor((2, 2), dir, 0, "."), (2, 2));
     }
 
+    #[test]
+    fn test_line_and_is_in_view() {
+        let (mut world, _) = fixtures();
+        assert_eq!(world.line((0, 0), (3, 0)), vec![(1, 0), (2, 0), (3, 0)]);
+        assert_eq!(world.line((0, 0), (2, 2)), vec![(1, 1), (2, 2)]);
+        assert!(world.line((2, 2), (2, 2)).is_empty());
+        assert!(world.is_in_view((0, 0), (4, 0)));
+        // the locked door at (1, 1) blocks sight along the diagonal:
+        assert!(!world.is_in_view((0, 0), (2, 2)));
+        assert!(world.is_in_view((0, 0), (1, 1)));
+        world.tiles[2] = 0; // a wall at (2, 0)
+        assert!(!world.is_in_view((0, 0), (4, 0)));
+    }
+
+    #[test]
+    fn test_direction_to() {
+        let (world, _) = fixtures();
+        assert_eq!(world.direction_to((2, 2), (2, 0)), Some(0));
+        assert_eq!(world.direction_to((0, 2), (4, 3)), Some(2));
+        assert_eq!(world.direction_to((2, 2), (0, 4)), Some(5));
+        assert_eq!(world.direction_to((2, 2), (2, 2)), None);
+    }
+
     #[test]
     fn test_change_tiles() {
         let (mut world, _) = fixtures();